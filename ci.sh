@@ -10,8 +10,14 @@ sh test-coverage.sh
 export CARGOFLAGS='--features=std --release'
 sh test-coverage.sh
 
+export CARGOFLAGS='--features=std,id-folding'
+sh test-coverage.sh
+
 export CARGOFLAGS='--no-default-features'
 sh test-coverage.sh
 
+export CARGOFLAGS='--no-default-features --features=alloc'
+sh test-coverage.sh
+
 export CARGOFLAGS='--no-default-features --release'
 sh test-coverage.sh
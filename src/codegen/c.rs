@@ -0,0 +1,339 @@
+//! C99 backend: `expr.to_c_fn("f", &["x", "y"])`, or `c_fn` for several outputs (e.g. an expression and its gradients) at once.
+//! Subexpressions appearing more than once (as they do all over gradients) are computed once, into `const` temporaries.
+//! The generated code expects `<math.h>`, `<stdbool.h>`, and `<stdint.h>` to be included already.
+
+use super::parameter;
+use crate::{
+    eval::Eval,
+    expr::Expr,
+    print::{
+        identity, is_empty, is_negative, Binary, Named, Names, Node, Shape, Unary, ATOM, PREFIX,
+    },
+};
+use core::fmt::Write;
+use std::collections::HashMap;
+
+impl<T: Eval + Node> Expr<T> {
+    /// Source of a standalone C99 function `name(params...)` returning this expression,
+    /// where parameter `i` is the variable tagged `i` (see `expr::var_id`).
+    #[inline]
+    pub fn to_c_fn(&self, name: &str, params: &[&str]) -> String {
+        c_function(name, params, &(), &[self], false)
+    }
+}
+
+impl<T: Node + ?Sized, N: Names + ?Sized> Named<'_, T, N> {
+    /// Source of a standalone C99 function `name(params...)` returning this expression,
+    /// where parameters are named variables (or, failing that, the variables tagged with their index).
+    #[inline]
+    pub fn to_c_fn(&self, name: &str, params: &[&str]) -> String {
+        c_function(name, params, self.names, &[self.expr], false)
+    }
+}
+
+/// Source of a standalone C99 function `void name(params..., out[])` writing each output (e.g. an expression, then its gradients) to `out`,
+/// sharing temporaries between them. Variables are named by `names` (use `&()` for none) or, failing that, tagged with their parameter's index.
+#[inline]
+pub fn c_fn<N: Names + ?Sized>(
+    name: &str,
+    params: &[&str],
+    names: &N,
+    outputs: &[&dyn Node],
+) -> String {
+    c_function(name, params, names, outputs, true)
+}
+
+/// Write the whole function, returning the only output or writing every output to `out`.
+fn c_function<N: Names + ?Sized, T: Node + ?Sized>(
+    name: &str,
+    params: &[&str],
+    names: &N,
+    outputs: &[&T],
+    array: bool,
+) -> String {
+    let ty = outputs
+        .first()
+        .map_or("double", |output| c_type(output.value_type()));
+    let mut emitter = Emitter {
+        names,
+        params,
+        counts: HashMap::new(),
+        temporaries: HashMap::new(),
+        body: String::new(),
+    };
+    for output in outputs {
+        let _ = emitter.count(*output);
+    }
+    let results: Vec<String> = outputs
+        .iter()
+        .map(|output| emitter.emit(*output, 0).0)
+        .collect();
+    let mut source = String::new();
+    let _ = write!(source, "{} {name}(", if array { "void" } else { ty });
+    for (i, param) in params.iter().enumerate() {
+        if i > 0 {
+            source.push_str(", ");
+        }
+        let _ = write!(source, "const {ty} {param}");
+    }
+    if array {
+        let _ = write!(
+            source,
+            "{}{ty} out[{}]",
+            if params.is_empty() { "" } else { ", " },
+            outputs.len()
+        );
+    } else if params.is_empty() {
+        source.push_str("void");
+    }
+    source.push_str(") {\n");
+    source.push_str(&emitter.body);
+    for (i, result) in results.iter().enumerate() {
+        if array {
+            let _ = writeln!(source, "    out[{i}] = {result};");
+        } else {
+            let _ = writeln!(source, "    return {result};");
+        }
+    }
+    source.push_str("}\n");
+    source
+}
+
+/// C spelling of a Rust primitive type (unknown types pass through, so they can be `typedef`d).
+#[inline]
+fn c_type(ty: &'static str) -> &'static str {
+    match ty {
+        "f64" => "double",
+        "f32" => "float",
+        "bool" => "bool",
+        "i8" => "int8_t",
+        "i16" => "int16_t",
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "isize" => "ptrdiff_t",
+        "u8" => "uint8_t",
+        "u16" => "uint16_t",
+        "u32" => "uint32_t",
+        "u64" => "uint64_t",
+        "usize" => "size_t",
+        _ => ty,
+    }
+}
+
+/// C infix operator and binding power (C's precedence orders these exactly like Rust's), or `None` for functions.
+#[inline]
+const fn operator(op: Binary, float: bool) -> Option<(&'static str, u8)> {
+    match op {
+        Binary::Mul => Some((" * ", 6)),
+        Binary::Div => Some((" / ", 6)),
+        Binary::Rem if float => None,
+        Binary::Powf | Binary::Atan2 => None,
+        _ => op.infix(),
+    }
+}
+
+/// Binding power of shifts, the loosest-binding operators that aren't bitwise.
+const SHIFT: u8 = 4;
+
+/// `math.h` name of a function on `double`s (`float`s take an `f` suffix).
+#[inline]
+const fn function(op: Shape<'_>) -> &'static str {
+    match op {
+        Shape::Unary(Unary::Ln, _) => "log",
+        Shape::Unary(op, _) => op.name(),
+        Shape::Binary(Binary::Rem, ..) => "fmod",
+        Shape::Binary(Binary::Powf, ..) | Shape::Powi(..) => "pow",
+        Shape::Binary(Binary::Atan2, ..) => "atan2",
+        Shape::Binary(..) | Shape::Nary(..) | Shape::Var { .. } | Shape::Const(_) => "",
+    }
+}
+
+/// Print a constant as a C literal of type `ty` (e.g. `2.0` rather than the integer `2`).
+fn literal(value: &dyn core::fmt::Display, ty: &str) -> String {
+    let text = value.to_string();
+    match (ty, text.as_str()) {
+        ("f64" | "f32", "inf") => "INFINITY".into(),
+        ("f64" | "f32", "-inf") => "-INFINITY".into(),
+        ("f64" | "f32", "NaN") => "NAN".into(),
+        ("f64" | "f32", _) => {
+            let point = if text.contains(['.', 'e']) { "" } else { ".0" };
+            format!("{text}{point}{}", if ty == "f32" { "f" } else { "" })
+        }
+        ("i64", _) => format!("{text}LL"),
+        ("u32" | "usize", _) => format!("{text}U"),
+        ("u64", _) => format!("{text}ULL"),
+        _ => text,
+    }
+}
+
+/// Walks trees twice: once to count each subexpression, then to print them, hoisting repeated ones into temporaries.
+struct Emitter<'a, N: ?Sized> {
+    names: &'a N,
+    params: &'a [&'a str],
+    /// Occurrences of each (fully parenthesized) subexpression.
+    counts: HashMap<String, usize>,
+    /// Temporary holding each repeated subexpression, once declared.
+    temporaries: HashMap<String, String>,
+    /// Temporary declarations.
+    body: String,
+}
+
+impl<N: Names + ?Sized> Emitter<'_, N> {
+    /// Print a variable or constant.
+    fn leaf(&self, node: &(impl Node + ?Sized)) -> String {
+        let shape = node.shape();
+        match (parameter(&shape, self.names, self.params), shape) {
+            (Some(name), _) => name.into(),
+            (None, Shape::Var { value, .. } | Shape::Const(value)) => {
+                literal(value, node.value_type())
+            }
+            (None, _) => unreachable!(),
+        }
+    }
+
+    /// Unambiguous key for a subexpression (counting every occurrence of every operation beneath it).
+    fn count(&mut self, node: &(impl Node + ?Sized)) -> String {
+        let key = match node.shape() {
+            Shape::Var { .. } | Shape::Const(_) => return self.leaf(node),
+            Shape::Unary(op, x) => format!("{}({})", op.name(), self.count(x)),
+            Shape::Binary(op, a, b) => {
+                format!("{}({}, {})", op.name(), self.count(a), self.count(b))
+            }
+            Shape::Powi(x, n) => format!("powi({}, {n})", self.count(x)),
+            Shape::Nary(op, nary) if is_empty(nary) => {
+                return literal(&identity(op), node.value_type())
+            }
+            Shape::Nary(op, nary) => {
+                let mut keys = vec![];
+                let _ = nary.try_for_each(&mut |x| {
+                    keys.push(self.count(x));
+                    Ok(())
+                });
+                format!("{}({})", op.name(), keys.join(", "))
+            }
+        };
+        let key = format!("{}:{key}", node.value_type());
+        *self.counts.entry(key.clone()).or_default() += 1;
+        key
+    }
+
+    /// Print `node`, parenthesized if it binds less tightly than `min`, along with its key.
+    fn emit(&mut self, node: &(impl Node + ?Sized), min: u8) -> (String, String) {
+        let ty = node.value_type();
+        let float = matches!(ty, "f64" | "f32");
+        let suffix = if ty == "f32" { "f" } else { "" };
+        let shape = node.shape();
+        let (text, power, key) = match shape {
+            Shape::Var { value, .. } | Shape::Const(value) => {
+                let text = self.leaf(node);
+                let power =
+                    if parameter(&shape, self.names, self.params).is_none() && is_negative(value) {
+                        PREFIX
+                    } else {
+                        ATOM
+                    };
+                (text.clone(), power, text)
+            }
+            Shape::Unary(op @ (Unary::Neg | Unary::Not), x) => {
+                // `-(-x)` rather than `--x`, which C reads as a decrement
+                let (inner, key) = self.emit(x, if op == Unary::Neg { ATOM } else { PREFIX });
+                let symbol = match op {
+                    Unary::Neg => "-",
+                    _ if ty == "bool" => "!",
+                    _ => "~",
+                };
+                (
+                    format!("{symbol}{inner}"),
+                    PREFIX,
+                    format!("{}({key})", op.name()),
+                )
+            }
+            Shape::Unary(op, x) => {
+                let (inner, key) = self.emit(x, 0);
+                (
+                    format!("{}{suffix}({inner})", function(shape)),
+                    ATOM,
+                    format!("{}({key})", op.name()),
+                )
+            }
+            Shape::Binary(op, a, b) => match operator(op, float) {
+                // Left-associative: only the right operand needs parentheses at equal binding power,
+                // except that compilers warn (`-Wparentheses`) about any infix operand of a shift or bitwise operator
+                Some((symbol, power)) => {
+                    let (left, right) = if power <= SHIFT {
+                        (PREFIX, PREFIX)
+                    } else {
+                        (power, power + 1)
+                    };
+                    let (left, a) = self.emit(a, left);
+                    let (right, b) = self.emit(b, right);
+                    (
+                        format!("{left}{symbol}{right}"),
+                        power,
+                        format!("{}({a}, {b})", op.name()),
+                    )
+                }
+                None => {
+                    let (left, a) = self.emit(a, 0);
+                    let (right, b) = self.emit(b, 0);
+                    (
+                        format!("{}{suffix}({left}, {right})", function(shape)),
+                        ATOM,
+                        format!("{}({a}, {b})", op.name()),
+                    )
+                }
+            },
+            Shape::Powi(x, n) => {
+                let (inner, key) = self.emit(x, 0);
+                (
+                    format!("pow{suffix}({inner}, {})", literal(&n, ty)),
+                    ATOM,
+                    format!("powi({key}, {n})"),
+                )
+            }
+            Shape::Nary(op, nary) => {
+                let (symbol, power) = operator(op, float).unwrap_or_default();
+                let mut texts = vec![];
+                let mut keys = vec![];
+                let _ = nary.try_for_each(&mut |x| {
+                    let (text, key) =
+                        self.emit(x, if texts.is_empty() { power } else { power + 1 });
+                    texts.push(text);
+                    keys.push(key);
+                    Ok(())
+                });
+                if texts.is_empty() {
+                    let text = literal(&identity(op), ty);
+                    (text.clone(), ATOM, text)
+                } else {
+                    (
+                        texts.join(symbol),
+                        power,
+                        format!("{}({})", op.name(), keys.join(", ")),
+                    )
+                }
+            }
+        };
+        let key = match shape {
+            Shape::Var { .. } | Shape::Const(_) => key,
+            Shape::Nary(_, nary) if is_empty(nary) => key,
+            _ => format!("{ty}:{key}"),
+        };
+        if self.counts.get(&key).copied().unwrap_or_default() > 1 {
+            let temporary = match self.temporaries.get(&key) {
+                Some(temporary) => temporary.clone(),
+                None => {
+                    let temporary = format!("t{}", self.temporaries.len());
+                    let _ = writeln!(self.body, "    const {} {temporary} = {text};", c_type(ty));
+                    self.temporaries.insert(key.clone(), temporary.clone());
+                    temporary
+                }
+            };
+            (temporary, key)
+        } else if power < min {
+            (format!("({text})"), key)
+        } else {
+            (text, key)
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Source code generation (with `std`): turn an expression, or its gradient, into a standalone function,
+//! e.g. from a `build.rs` that writes derivative code to a file, so the shipped program doesn't need `dxpr` at all.
+//!
+//! Function parameters are the variables tagged with IDs (parameter `i` is `var_id::<i, _>`) or, through `Expr::named`, named after one.
+//! Any other variable is baked into the generated code as a constant, its current value.
+
+mod c;
+mod rust;
+
+pub use c::c_fn;
+
+use crate::print::{Names, Shape};
+
+/// Name of the parameter a variable reads from, or `None` to bake in its current value.
+#[inline]
+fn parameter<'a, N: Names + ?Sized>(
+    shape: &Shape<'_>,
+    names: &'a N,
+    params: &[&'a str],
+) -> Option<&'a str> {
+    match *shape {
+        Shape::Var { ptr, id, .. } => names
+            .name(ptr)
+            .filter(|name| params.contains(name))
+            .or_else(|| id.and_then(|id| params.get(id).copied())),
+        _ => None,
+    }
+}
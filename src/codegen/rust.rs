@@ -0,0 +1,195 @@
+//! Rust backend: `expr.to_rust_fn("f", &["x", "y"])`.
+
+use super::parameter;
+use crate::{
+    eval::Eval,
+    expr::Expr,
+    print::{
+        identity, is_empty, is_negative, Binary, Named, Names, Node, Shape, Unary, ATOM, PREFIX,
+    },
+};
+use core::fmt::{self, Display, Write};
+
+impl<T: Eval + Node> Expr<T> {
+    /// Source of a standalone Rust function `name(params...)` computing this expression,
+    /// where parameter `i` is the variable tagged `i` (see `expr::var_id`).
+    /// A `const fn` unless the expression calls functions (e.g. `sin`) that aren't `const` in `core`.
+    #[inline]
+    pub fn to_rust_fn(&self, name: &str, params: &[&str]) -> String {
+        rust_fn(self, &(), name, params)
+    }
+}
+
+impl<T: Node + ?Sized, N: Names + ?Sized> Named<'_, T, N> {
+    /// Source of a standalone Rust function `name(params...)` computing this expression,
+    /// where parameters are named variables (or, failing that, the variables tagged with their index).
+    #[inline]
+    pub fn to_rust_fn(&self, name: &str, params: &[&str]) -> String {
+        rust_fn(self.expr, self.names, name, params)
+    }
+}
+
+/// Write the whole function.
+fn rust_fn<N: Names + ?Sized>(
+    node: &(impl Node + ?Sized),
+    names: &N,
+    name: &str,
+    params: &[&str],
+) -> String {
+    let mut body = String::new();
+    let mut used = vec![false; params.len()];
+    let mut constant = true;
+    // Writing to a `String` can't fail
+    let _ = rust(node, names, params, &mut used, &mut constant, 0, &mut body);
+    let ty = node.value_type();
+    let mut source = String::new();
+    if used.contains(&false) {
+        source.push_str("#[allow(unused_variables)]\n");
+    }
+    source.push_str(if constant { "pub const fn " } else { "pub fn " });
+    source.push_str(name);
+    source.push('(');
+    for (i, param) in params.iter().enumerate() {
+        if i > 0 {
+            source.push_str(", ");
+        }
+        let _ = write!(source, "{param}: {ty}");
+    }
+    let _ = write!(source, ") -> {ty} {{\n    {body}\n}}\n");
+    source
+}
+
+/// Rust infix operator and binding power (Rust's own precedence, as in `Binary::infix`), or `None` for methods.
+#[inline]
+const fn operator(op: Binary) -> Option<(&'static str, u8)> {
+    match op {
+        Binary::Mul => Some((" * ", 6)),
+        Binary::Div => Some((" / ", 6)),
+        Binary::Powf | Binary::Atan2 => None,
+        _ => op.infix(),
+    }
+}
+
+/// Binding power of a node as printed in Rust.
+fn binding<N: Names + ?Sized>(node: &(impl Node + ?Sized), names: &N, params: &[&str]) -> u8 {
+    let shape = node.shape();
+    match shape {
+        Shape::Var { .. } if parameter(&shape, names, params).is_some() => ATOM,
+        Shape::Var { value, .. } | Shape::Const(value) if is_negative(value) => PREFIX,
+        Shape::Unary(Unary::Neg | Unary::Not, _) => PREFIX,
+        Shape::Binary(op, ..) => operator(op).map_or(ATOM, |(_, power)| power),
+        Shape::Nary(op, nary) if !is_empty(nary) => operator(op).map_or(ATOM, |(_, power)| power),
+        _ => ATOM,
+    }
+}
+
+/// Primitive types whose literals take a suffix.
+const NUMERIC: [&str; 14] = [
+    "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128",
+    "usize",
+];
+
+/// Print a constant with a type suffix (e.g. `2f64`), so it isn't inferred as `i32` or `f64` instead.
+fn literal(value: &dyn Display, ty: &str, out: &mut String) -> fmt::Result {
+    let start = out.len();
+    write!(out, "{value}")?;
+    let special = match &out[start..] {
+        "inf" => Some("INFINITY"),
+        "-inf" => Some("NEG_INFINITY"),
+        "NaN" => Some("NAN"),
+        _ => None,
+    };
+    match special {
+        Some(constant) => {
+            out.truncate(start);
+            write!(out, "{ty}::{constant}")
+        }
+        None if NUMERIC.contains(&ty) => out.write_str(ty),
+        None => Ok(()),
+    }
+}
+
+/// Print `node`, parenthesized if it binds less tightly than `min`.
+fn rust<N: Names + ?Sized>(
+    node: &(impl Node + ?Sized),
+    names: &N,
+    params: &[&str],
+    used: &mut [bool],
+    constant: &mut bool,
+    min: u8,
+    out: &mut String,
+) -> fmt::Result {
+    if binding(node, names, params) < min {
+        out.push('(');
+        rust(node, names, params, used, constant, 0, out)?;
+        out.push(')');
+        return Ok(());
+    }
+    let shape = node.shape();
+    match shape {
+        Shape::Var { value, .. } => match parameter(&shape, names, params) {
+            Some(name) => {
+                if let Some(i) = params.iter().position(|&param| param == name) {
+                    used[i] = true;
+                }
+                out.push_str(name);
+                Ok(())
+            }
+            None => literal(value, node.value_type(), out),
+        },
+        Shape::Const(value) => literal(value, node.value_type(), out),
+        Shape::Unary(op @ (Unary::Neg | Unary::Not), x) => {
+            // `-(-x)` rather than `--x`
+            out.push(if op == Unary::Neg { '-' } else { '!' });
+            let min = if op == Unary::Neg { ATOM } else { PREFIX };
+            rust(x, names, params, used, constant, min, out)
+        }
+        Shape::Unary(op, x) => {
+            *constant = false;
+            rust(x, names, params, used, constant, ATOM, out)?;
+            write!(out, ".{}()", op.name())
+        }
+        Shape::Binary(op, a, b) => match operator(op) {
+            // Left-associative: only the right operand needs parentheses at equal binding power
+            Some((symbol, power)) => {
+                rust(a, names, params, used, constant, power, out)?;
+                out.push_str(symbol);
+                rust(b, names, params, used, constant, power + 1, out)
+            }
+            None => {
+                *constant = false;
+                rust(a, names, params, used, constant, ATOM, out)?;
+                out.push_str(if op == Binary::Powf {
+                    ".powf("
+                } else {
+                    ".atan2("
+                });
+                rust(b, names, params, used, constant, 0, out)?;
+                out.push(')');
+                Ok(())
+            }
+        },
+        Shape::Powi(x, n) => {
+            *constant = false;
+            rust(x, names, params, used, constant, ATOM, out)?;
+            write!(out, ".powi({n})")
+        }
+        Shape::Nary(op, nary) => {
+            let (symbol, power) = operator(op).unwrap_or_default();
+            let mut empty = true;
+            nary.try_for_each(&mut |x| {
+                if !empty {
+                    out.push_str(symbol);
+                }
+                let min = if empty { power } else { power + 1 };
+                rust(x, names, params, used, constant, min, out)?;
+                empty = false;
+                Ok(())
+            })?;
+            if empty {
+                literal(&identity(op), node.value_type(), out)?;
+            }
+            Ok(())
+        }
+    }
+}
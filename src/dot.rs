@@ -0,0 +1,179 @@
+//! Graphviz output (e.g. `expr.to_dot()`), to see the tree `grad` built: one graph node per operation or constant,
+//! with every use of a variable pointing at the same node (so shared variables show up as a DAG).
+//!
+//! Optionally labels every node with what it evaluates to (`expr.dot().values()`); each node evaluates its own subtree,
+//! so expect quadratic time on deep trees.
+
+use crate::{
+    eval::Eval,
+    expr::Expr,
+    print::{Named, Names, Node, Shape},
+};
+use core::fmt::{self, Display, Formatter, Write};
+
+/// Expression displayed as a Graphviz `digraph` (see `Expr::dot`).
+pub struct Dot<'a, T: ?Sized, N: ?Sized> {
+    expr: &'a T,
+    names: &'a N,
+    values: bool,
+}
+
+impl<T: Eval + Node> Expr<T> {
+    /// Display as a Graphviz `digraph`, with variables labelled by their values.
+    #[inline(always)]
+    pub fn dot(&self) -> Dot<'_, Self, ()> {
+        Dot {
+            expr: self,
+            names: &(),
+            values: false,
+        }
+    }
+    /// Graphviz source, with variables labelled by their values.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    pub fn to_dot(&self) -> String {
+        self.dot().to_string()
+    }
+}
+
+impl<'a, T: Node + ?Sized, N: Names + ?Sized> Named<'a, T, N> {
+    /// Display as a Graphviz `digraph`, with variables labelled by name.
+    #[inline(always)]
+    pub fn dot(&self) -> Dot<'a, T, N> {
+        Dot {
+            expr: self.expr,
+            names: self.names,
+            values: false,
+        }
+    }
+    /// Graphviz source, with variables labelled by name.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    pub fn to_dot(&self) -> String {
+        self.dot().to_string()
+    }
+}
+
+impl<'a, T: ?Sized, N: ?Sized> Dot<'a, T, N> {
+    /// Also label every node with what it evaluates to.
+    #[inline(always)]
+    pub fn values(self) -> Self {
+        Dot {
+            values: true,
+            ..self
+        }
+    }
+}
+
+impl<T: Node + ?Sized, N: Names + ?Sized> Display for Dot<'_, T, N> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("digraph {\n")?;
+        let mut next = 0;
+        let _ = graph(self.expr, self.names, self.values, &mut next, f)?;
+        f.write_str("}\n")
+    }
+}
+
+/// Graphviz node ID: operations and constants are numbered, but variables are keyed by address so every use shares one node.
+#[derive(Clone, Copy)]
+enum Id {
+    Numbered(usize),
+    Var(*const ()),
+}
+
+impl Display for Id {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Id::Numbered(n) => write!(f, "n{n}"),
+            Id::Var(ptr) => write!(f, "\"{ptr:p}\""),
+        }
+    }
+}
+
+/// Writer escaping quotes and backslashes inside a quoted Graphviz string.
+struct Escape<'a, 'b>(&'a mut Formatter<'b>);
+
+impl Write for Escape<'_, '_> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if matches!(c, '"' | '\\') {
+                self.0.write_char('\\')?;
+            }
+            self.0.write_char(c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `node` and its subtree, returning its ID so the parent can point at it.
+fn graph<N: Names + ?Sized>(
+    node: &(impl Node + ?Sized),
+    names: &N,
+    values: bool,
+    next: &mut usize,
+    f: &mut Formatter<'_>,
+) -> Result<Id, fmt::Error> {
+    let shape = node.shape();
+    let id = match shape {
+        Shape::Var { ptr, .. } => Id::Var(ptr),
+        _ => {
+            *next += 1;
+            Id::Numbered(*next - 1)
+        }
+    };
+    write!(f, "    {id} [label=\"")?;
+    // Constants and unnamed variables are labelled by their values anyway
+    let mut labelled_by_value = false;
+    match shape {
+        Shape::Var { ptr, id, value } => match (names.name(ptr), id) {
+            (Some(name), _) => Escape(f).write_str(name)?,
+            (None, Some(id)) => {
+                write!(Escape(f), "var {id} = {value}")?;
+                labelled_by_value = true;
+            }
+            (None, None) => {
+                write!(Escape(f), "{value}")?;
+                labelled_by_value = true;
+            }
+        },
+        Shape::Const(value) => {
+            write!(Escape(f), "{value}")?;
+            labelled_by_value = true;
+        }
+        Shape::Unary(op, _) => f.write_str(op.name())?,
+        Shape::Binary(op, ..) | Shape::Nary(op, _) => f.write_str(op.name())?,
+        Shape::Powi(_, n) => write!(f, "powi {n}")?,
+    }
+    if values && !labelled_by_value {
+        f.write_str("\\n= ")?;
+        write!(Escape(f), "{}", Value(node))?;
+    }
+    f.write_str("\"];\n")?;
+    let mut edge = |child: &dyn Node, f: &mut Formatter<'_>| -> fmt::Result {
+        let child = graph(child, names, values, next, f)?;
+        writeln!(f, "    {id} -> {child};")
+    };
+    match shape {
+        Shape::Var { .. } | Shape::Const(_) => {}
+        Shape::Unary(_, x) | Shape::Powi(x, _) => edge(x, f)?,
+        Shape::Binary(_, a, b) => {
+            edge(a, f)?;
+            edge(b, f)?;
+        }
+        Shape::Nary(_, nary) => nary.try_for_each(&mut |x| edge(x, f))?,
+    }
+    Ok(id)
+}
+
+/// Display what a node evaluates to.
+struct Value<'a, T: ?Sized>(&'a T);
+
+impl<T: Node + ?Sized> Display for Value<'_, T> {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt_value(f)
+    }
+}
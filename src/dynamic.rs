@@ -0,0 +1,1004 @@
+//! Expressions whose shape isn't known to the type system (with `alloc`): built at runtime (e.g. by `parse::parse` or in a loop),
+//! chosen at runtime, or stored together in a `Vec`.
+//! `DynExpr` is one heap-allocated tree type for every expression, so it costs allocations and dispatch,
+//! but it evaluates, differentiates, and prints like any other node.
+//!
+//! Any static expression converts (`DynExpr::from(expr)` or `expr.into_dyn()`), and the usual operators mix the two on either side,
+//! e.g. `dynamic * var(&z).sin()` or `var(&z).sin() * dynamic`, always into a `DynExpr`.
+//!
+//! `sum` and `product` (or `Iterator::sum` and `Iterator::product`) build flat nodes out of any number of terms.
+//!
+//! Only arithmetic and `float::Float` functions are supported (see `Real`): bitwise operations don't make sense on the floating-point values these are mostly for.
+//! Powers and functions need `std` (`core` has no math library), so with only `alloc`, a `DynExpr<f64>` does arithmetic alone, like one over integers.
+
+use crate::{
+    eval::Eval,
+    expr::Expr,
+    float::Float,
+    grad::Wrt,
+    leaf::{Delta, Leaf, Lit, One, Var, Zero},
+    ops::{self, collapse::Dynamic},
+    print::{Binary, Named, Names, Node, Operands, Shape, Unary},
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    fmt::{self, Display, Formatter},
+    ops::{Add, Div, Mul, Neg, Rem, Sub},
+};
+
+/// Values a `DynExpr` can compute with: anything with arithmetic (e.g. `i32` or `f64`).
+/// Anything `float::Float` (e.g. `f32` and `f64` with `std`) also computes powers and function calls (see `FLOAT`);
+/// the rest (integers, and `f32` and `f64` without `std`) can't, so the checked constructors (e.g. `DynExpr::call`) refuse to build them.
+pub trait Real:
+    Leaf<Unit = &'static Self>
+    + Neg<Output = Self>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+    + PartialEq
+    + Copy
+    + 'static
+{
+    /// Whether `powf`, `powi`, `atan2`, and `call` work, rather than panic.
+    const FLOAT: bool;
+    /// Convert a (small) integer, e.g. the `2` in `d/dx sqrt(x) = 1 / (2 sqrt(x))`.
+    fn from_i32(n: i32) -> Self;
+    /// `self^n` for floating-point `n`.
+    fn powf(self, n: Self) -> Self;
+    /// `self^n` for integral `n`.
+    fn powi(self, n: i32) -> Self;
+    /// Four-quadrant inverse tangent of `self / x` (in radians).
+    fn atan2(self, x: Self) -> Self;
+    /// Apply a function of one argument.
+    fn call(self, f: Function) -> Self;
+}
+
+impl<T> Real for T
+where
+    T: Float<Value = T>
+        + Leaf<Unit = &'static T>
+        + Neg<Output = T>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + Rem<Output = T>
+        + PartialEq
+        + 'static,
+{
+    const FLOAT: bool = true;
+    #[inline(always)]
+    fn from_i32(n: i32) -> Self {
+        <T as Float>::from_i32(n)
+    }
+    #[inline(always)]
+    fn powf(self, n: Self) -> Self {
+        Float::powf(self, n)
+    }
+    #[inline(always)]
+    fn powi(self, n: i32) -> Self {
+        Float::powi(self, n)
+    }
+    #[inline(always)]
+    fn atan2(self, x: Self) -> Self {
+        Float::atan2(self, x)
+    }
+    #[inline(always)]
+    fn call(self, f: Function) -> Self {
+        f.apply(self)
+    }
+}
+
+/// Arithmetic only.
+macro_rules! implement_real_arithmetic {
+    ($($t:ty),+) => {
+        $(
+            impl Real for $t {
+                const FLOAT: bool = false;
+                #[inline(always)]
+                fn from_i32(n: i32) -> Self {
+                    n as $t
+                }
+                #[inline(always)]
+                fn powf(self, _n: Self) -> Self {
+                    not_float("powf")
+                }
+                #[inline(always)]
+                fn powi(self, _n: i32) -> Self {
+                    not_float("powi")
+                }
+                #[inline(always)]
+                fn atan2(self, _x: Self) -> Self {
+                    not_float("atan2")
+                }
+                #[inline(always)]
+                fn call(self, f: Function) -> Self {
+                    not_float(f.name())
+                }
+            }
+        )+
+    };
+}
+
+implement_real_arithmetic!(i8, i16, i32, i64, i128);
+// No math library to call into
+#[cfg(not(feature = "std"))]
+implement_real_arithmetic!(f32, f64);
+
+#[cold]
+fn not_float(function: &'static str) -> ! {
+    panic!("{}", Unsupported(function))
+}
+
+/// Power or function call (named here) over values that only have arithmetic (see `Real::FLOAT`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Unsupported(pub &'static str);
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` in a `DynExpr` over values without floating-point functions",
+            self.0
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Unsupported {}
+
+/// Function of one argument.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Function {
+    /// `exp(x)`
+    Exp,
+    /// `ln(x)`
+    Ln,
+    /// `sqrt(x)`
+    Sqrt,
+    /// `sin(x)`
+    Sin,
+    /// `cos(x)`
+    Cos,
+    /// `tan(x)`
+    Tan,
+    /// `asin(x)`
+    Asin,
+    /// `acos(x)`
+    Acos,
+    /// `atan(x)`
+    Atan,
+    /// `sinh(x)`
+    Sinh,
+    /// `cosh(x)`
+    Cosh,
+    /// `tanh(x)`
+    Tanh,
+}
+
+impl Function {
+    /// Every function, e.g. to look one up by name.
+    pub const ALL: [Function; 12] = [
+        Function::Exp,
+        Function::Ln,
+        Function::Sqrt,
+        Function::Sin,
+        Function::Cos,
+        Function::Tan,
+        Function::Asin,
+        Function::Acos,
+        Function::Atan,
+        Function::Sinh,
+        Function::Cosh,
+        Function::Tanh,
+    ];
+    /// The same function as a `print::Unary`.
+    #[inline]
+    pub const fn unary(self) -> Unary {
+        match self {
+            Function::Exp => Unary::Exp,
+            Function::Ln => Unary::Ln,
+            Function::Sqrt => Unary::Sqrt,
+            Function::Sin => Unary::Sin,
+            Function::Cos => Unary::Cos,
+            Function::Tan => Unary::Tan,
+            Function::Asin => Unary::Asin,
+            Function::Acos => Unary::Acos,
+            Function::Atan => Unary::Atan,
+            Function::Sinh => Unary::Sinh,
+            Function::Cosh => Unary::Cosh,
+            Function::Tanh => Unary::Tanh,
+        }
+    }
+    /// Name (e.g. `"sin"`).
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        self.unary().name()
+    }
+    /// Apply to a value.
+    #[inline]
+    pub fn apply<T: Float>(self, x: T) -> T::Value {
+        match self {
+            Function::Exp => x.exp(),
+            Function::Ln => x.ln(),
+            Function::Sqrt => x.sqrt(),
+            Function::Sin => x.sin(),
+            Function::Cos => x.cos(),
+            Function::Tan => x.tan(),
+            Function::Asin => x.asin(),
+            Function::Acos => x.acos(),
+            Function::Atan => x.atan(),
+            Function::Sinh => x.sinh(),
+            Function::Cosh => x.cosh(),
+            Function::Tanh => x.tanh(),
+        }
+    }
+}
+
+/// Expression tree built at runtime over variables borrowed for `'a`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynExpr<'a, T> {
+    /// Variable (compared by address, like `expr::var`), tagged with an ID if it came from `expr::var_id`.
+    Var(&'a T, Option<usize>),
+    /// Constant.
+    Lit(T),
+    /// `-a`
+    Neg(Box<Self>),
+    /// `a + b`
+    Add(Box<Self>, Box<Self>),
+    /// `a - b`
+    Sub(Box<Self>, Box<Self>),
+    /// `a * b`
+    Mul(Box<Self>, Box<Self>),
+    /// `a * b`, but zero wherever `a` is, even if `b` is infinite (see `ops::ZeroMul`)
+    ZeroMul(Box<Self>, Box<Self>),
+    /// `a / b`
+    Div(Box<Self>, Box<Self>),
+    /// `a % b`
+    Rem(Box<Self>, Box<Self>),
+    /// `a^b` for floating-point `b` (see `DynExpr::powf`)
+    Powf(Box<Self>, Box<Self>),
+    /// `a^n` for integral `n` (see `DynExpr::powi`)
+    Powi(Box<Self>, i32),
+    /// Four-quadrant inverse tangent of `a / b` (see `DynExpr::atan2`)
+    Atan2(Box<Self>, Box<Self>),
+    /// Function of one argument (see `DynExpr::call`)
+    Call(Function, Box<Self>),
+    /// Flat sum of any number of terms (see `sum`)
+    Sum(Vec<Self>),
+    /// Flat product of any number of factors (see `product`)
+    Product(Vec<Self>),
+    /// Generalized product rule over factors and their gradients, each gradient times all the other factors:
+    /// what a `Product` differentiates to (see `ops::ProductRule`)
+    ProductRule(Vec<Self>, Vec<Self>),
+}
+
+impl<'a, T: Real> DynExpr<'a, T> {
+    /// `a^b` for floating-point `b`, unless `T` can't compute it (see `Real::FLOAT`).
+    #[inline]
+    pub fn powf(a: impl IntoDyn<'a, T>, b: impl IntoDyn<'a, T>) -> Result<Self, Unsupported> {
+        Self::float("powf")?;
+        Ok(DynExpr::Powf(
+            Box::new(a.into_dyn()),
+            Box::new(b.into_dyn()),
+        ))
+    }
+    /// `a^n` for integral `n`, unless `T` can't compute it (see `Real::FLOAT`).
+    #[inline]
+    pub fn powi(a: impl IntoDyn<'a, T>, n: i32) -> Result<Self, Unsupported> {
+        Self::float("powi")?;
+        Ok(DynExpr::Powi(Box::new(a.into_dyn()), n))
+    }
+    /// Four-quadrant inverse tangent of `a / b`, unless `T` can't compute it (see `Real::FLOAT`).
+    #[inline]
+    pub fn atan2(a: impl IntoDyn<'a, T>, b: impl IntoDyn<'a, T>) -> Result<Self, Unsupported> {
+        Self::float("atan2")?;
+        Ok(DynExpr::Atan2(
+            Box::new(a.into_dyn()),
+            Box::new(b.into_dyn()),
+        ))
+    }
+    /// `f(a)`, unless `T` can't compute it (see `Real::FLOAT`).
+    #[inline]
+    pub fn call(f: Function, a: impl IntoDyn<'a, T>) -> Result<Self, Unsupported> {
+        Self::float(f.name())?;
+        Ok(DynExpr::Call(f, Box::new(a.into_dyn())))
+    }
+    /// Refuse `function` unless `T` has floating-point functions.
+    #[inline(always)]
+    fn float(function: &'static str) -> Result<(), Unsupported> {
+        if T::FLOAT {
+            Ok(())
+        } else {
+            Err(Unsupported(function))
+        }
+    }
+
+    /// Whether this is the constant `Leaf::ZERO`.
+    #[inline]
+    fn is_zero(&self) -> bool {
+        matches!(self, DynExpr::Lit(value) if value == T::ZERO)
+    }
+    /// Whether this is the constant `Leaf::UNIT`.
+    #[inline]
+    fn is_one(&self) -> bool {
+        matches!(self, DynExpr::Lit(value) if value == T::UNIT)
+    }
+    /// Constant `Leaf::ZERO`.
+    #[inline]
+    fn zero() -> Self {
+        DynExpr::Lit(*T::ZERO)
+    }
+    /// `-a`, folding zeros (like `ops::collapse::Neg`).
+    #[inline]
+    fn negate(a: Self) -> Self {
+        if a.is_zero() {
+            a
+        } else {
+            DynExpr::Neg(Box::new(a))
+        }
+    }
+    /// `a + b`, dropping zeros (like `ops::collapse::Add`).
+    #[inline]
+    fn plus(a: Self, b: Self) -> Self {
+        match (a.is_zero(), b.is_zero()) {
+            (true, _) => b,
+            (_, true) => a,
+            _ => DynExpr::Add(Box::new(a), Box::new(b)),
+        }
+    }
+    /// `a - b`, dropping zeros (like `ops::collapse::Sub`).
+    #[inline]
+    fn minus(a: Self, b: Self) -> Self {
+        match (a.is_zero(), b.is_zero()) {
+            (_, true) => a,
+            (true, _) => Self::negate(b),
+            _ => DynExpr::Sub(Box::new(a), Box::new(b)),
+        }
+    }
+    /// `a * b`, folding zeros and ones (like `ops::collapse::Mul`).
+    #[inline]
+    fn times(a: Self, b: Self) -> Self {
+        if a.is_zero() || b.is_zero() {
+            Self::zero()
+        } else if a.is_one() {
+            b
+        } else if b.is_one() {
+            a
+        } else {
+            DynExpr::Mul(Box::new(a), Box::new(b))
+        }
+    }
+    /// Flat sum, dropping zeros.
+    #[inline]
+    fn total(terms: Vec<Self>) -> Self {
+        let mut terms: Vec<Self> = terms.into_iter().filter(|t| !t.is_zero()).collect();
+        match terms.len() {
+            0 => Self::zero(),
+            1 => terms.pop().expect("one term"),
+            _ => DynExpr::Sum(terms),
+        }
+    }
+    /// Product rule over `factors` and their `grads`, folding to zero if every gradient is zero.
+    #[inline]
+    fn rule(factors: Vec<Self>, grads: Vec<Self>) -> Self {
+        if grads.iter().all(Self::is_zero) {
+            Self::zero()
+        } else {
+            DynExpr::ProductRule(factors, grads)
+        }
+    }
+    /// `a / b`, folding zeros and ones (like `ops::collapse::Div`).
+    #[inline]
+    fn over(a: Self, b: Self) -> Self {
+        if a.is_zero() {
+            Self::zero()
+        } else if b.is_one() {
+            a
+        } else {
+            DynExpr::Div(Box::new(a), Box::new(b))
+        }
+    }
+
+    /// `a^n` for an exponent that may not fit an `i32`, split in two like `float::powi_wide`.
+    #[inline]
+    fn power(a: Self, n: i64) -> Self {
+        match crate::float::split_exponent(n) {
+            (n, None) => DynExpr::Powi(Box::new(a), n),
+            (m, Some(n)) => DynExpr::Mul(
+                Box::new(DynExpr::Powi(Box::new(a.clone()), m)),
+                Box::new(DynExpr::Powi(Box::new(a), n)),
+            ),
+        }
+    }
+
+    /// Fold identities (`x + 0`, `x * 1`, `--x`, ...) and constant arithmetic, checking literals against their actual values.
+    /// Unlike `simplify::Simplify`, this sees runtime constants too, e.g. the `Delta`s that `grad` leaves behind
+    /// (see `IntoDyn::simplify_dyn`).
+    /// Only `+`, `-`, and `*` fold constants, so e.g. an integer division by zero still panics only when evaluated.
+    pub fn simplify(self) -> Self {
+        let simple = |a: Box<Self>| a.simplify();
+        let simplified = match self {
+            DynExpr::Var(..) | DynExpr::Lit(_) => return self,
+            DynExpr::Neg(a) => match a.simplify() {
+                DynExpr::Neg(a) => return *a,
+                a => Self::negate(a),
+            },
+            DynExpr::Add(a, b) => Self::plus(simple(a), simple(b)),
+            DynExpr::Sub(a, b) => Self::minus(simple(a), simple(b)),
+            DynExpr::Mul(a, b) => Self::times(simple(a), simple(b)),
+            DynExpr::ZeroMul(a, b) => match simple(a) {
+                a if a.is_zero() => a,
+                a if a.is_one() => simple(b),
+                a => DynExpr::ZeroMul(Box::new(a), Box::new(simple(b))),
+            },
+            DynExpr::Div(a, b) => Self::over(simple(a), simple(b)),
+            DynExpr::Rem(a, b) => DynExpr::Rem(Box::new(simple(a)), Box::new(simple(b))),
+            DynExpr::Powf(a, b) => DynExpr::Powf(Box::new(simple(a)), Box::new(simple(b))),
+            DynExpr::Powi(a, 1) => simple(a),
+            DynExpr::Powi(a, n) => DynExpr::Powi(Box::new(simple(a)), n),
+            DynExpr::Atan2(a, b) => DynExpr::Atan2(Box::new(simple(a)), Box::new(simple(b))),
+            DynExpr::Call(f, a) => DynExpr::Call(f, Box::new(simple(a))),
+            DynExpr::Sum(terms) => Self::total(terms.into_iter().map(Self::simplify).collect()),
+            DynExpr::Product(factors) => {
+                let factors: Vec<Self> = factors.into_iter().map(Self::simplify).collect();
+                Self::factor(factors)
+            }
+            // A single nonzero gradient is just a product; otherwise keep the rule in one node
+            DynExpr::ProductRule(factors, grads) => {
+                let mut factors: Vec<Self> = factors.into_iter().map(Self::simplify).collect();
+                let grads: Vec<Self> = grads.into_iter().map(Self::simplify).collect();
+                let mut nonzero = grads.iter().enumerate().filter(|(_, g)| !g.is_zero());
+                match (nonzero.next(), nonzero.next()) {
+                    (None, _) => Self::zero(),
+                    (Some((i, g)), None) => {
+                        let g = g.clone();
+                        factors.remove(i);
+                        Self::times(g, Self::factor(factors))
+                    }
+                    _ => DynExpr::ProductRule(factors, grads),
+                }
+            }
+        };
+        // Constant arithmetic folds into one literal
+        let lit = |a: &Self| matches!(a, DynExpr::Lit(_));
+        let constant = match &simplified {
+            DynExpr::Neg(a) => lit(a),
+            DynExpr::Add(a, b) | DynExpr::Sub(a, b) | DynExpr::Mul(a, b) => lit(a) && lit(b),
+            DynExpr::Sum(xs) | DynExpr::Product(xs) => xs.iter().all(lit),
+            _ => false,
+        };
+        if constant {
+            DynExpr::Lit(simplified.value())
+        } else {
+            simplified
+        }
+    }
+    /// Flat product, dropping ones and folding to zero if any factor is zero.
+    #[inline]
+    fn factor(factors: Vec<Self>) -> Self {
+        if factors.iter().any(Self::is_zero) {
+            return Self::zero();
+        }
+        let mut factors: Vec<Self> = factors.into_iter().filter(|f| !f.is_one()).collect();
+        match factors.len() {
+            0 => DynExpr::Lit(*T::UNIT),
+            1 => factors.pop().expect("one factor"),
+            _ => DynExpr::Product(factors),
+        }
+    }
+
+    /// Evaluate (what `eval` calls).
+    /// Panics on a power or function call over values without them (see `Real::FLOAT`),
+    /// which only a variant built by hand, rather than with e.g. `DynExpr::call`, can hold.
+    pub fn value(&self) -> T {
+        match self {
+            DynExpr::Var(x, _) => **x,
+            DynExpr::Lit(c) => *c,
+            DynExpr::Neg(a) => -a.value(),
+            DynExpr::Add(a, b) => a.value() + b.value(),
+            DynExpr::Sub(a, b) => a.value() - b.value(),
+            DynExpr::Mul(a, b) => a.value() * b.value(),
+            DynExpr::ZeroMul(a, b) => match a.value() {
+                a if a == *T::ZERO => a,
+                a => a * b.value(),
+            },
+            DynExpr::Div(a, b) => a.value() / b.value(),
+            DynExpr::Rem(a, b) => a.value() % b.value(),
+            DynExpr::Powf(a, b) => a.value().powf(b.value()),
+            DynExpr::Powi(a, n) => a.value().powi(*n),
+            DynExpr::Atan2(a, b) => a.value().atan2(b.value()),
+            DynExpr::Call(f, a) => a.value().call(*f),
+            DynExpr::Sum(terms) => terms.iter().fold(*T::ZERO, |sum, t| sum + t.value()),
+            DynExpr::Product(factors) => factors
+                .iter()
+                .fold(*T::UNIT, |product, f| product * f.value()),
+            // Running product and product rule, factor by factor, like `ops::ProductRule`
+            DynExpr::ProductRule(factors, grads) => {
+                let pairs = factors.iter().zip(grads);
+                pairs
+                    .fold((*T::UNIT, *T::ZERO), |(product, rule), (f, g)| {
+                        let f = f.value();
+                        (product * f, rule * f + product * g.value())
+                    })
+                    .1
+            }
+        }
+    }
+
+    /// Differentiate (what `grad` calls), with the same rules as the static nodes in `ops`.
+    pub fn derivative<W: Wrt>(&self, x: W) -> Self {
+        let boxed = |a: &Self| Box::new(a.clone());
+        let lit = |n: i32| DynExpr::Lit(T::from_i32(n));
+        match self {
+            DynExpr::Var(v, id) => {
+                DynExpr::Lit(if id.map_or(false, |id| x.is_id(id)) || x.is_ptr(*v) {
+                    *T::UNIT
+                } else {
+                    *T::ZERO
+                })
+            }
+            DynExpr::Lit(_) => Self::zero(),
+            DynExpr::Neg(a) => Self::negate(a.derivative(x)),
+            DynExpr::Add(a, b) => Self::plus(a.derivative(x), b.derivative(x)),
+            DynExpr::Sub(a, b) => Self::minus(a.derivative(x), b.derivative(x)),
+            DynExpr::Mul(a, b) | DynExpr::ZeroMul(a, b) => Self::plus(
+                Self::times(a.derivative(x), (**b).clone()),
+                Self::times((**a).clone(), b.derivative(x)),
+            ),
+            DynExpr::Div(a, b) => Self::over(
+                Self::minus(
+                    Self::times(a.derivative(x), (**b).clone()),
+                    Self::times((**a).clone(), b.derivative(x)),
+                ),
+                DynExpr::Mul(boxed(b), boxed(b)),
+            ),
+            // a' - q b', where q is the (truncated) quotient (a - a % b) / b
+            DynExpr::Rem(a, b) => Self::minus(
+                a.derivative(x),
+                Self::times(
+                    DynExpr::Div(Box::new(DynExpr::Sub(boxed(a), boxed(self))), boxed(b)),
+                    b.derivative(x),
+                ),
+            ),
+            // b a^(b - 1) a' + a^b ln a b', without the `ln a` term for constant b (since e.g. `0^2` would be `NaN`),
+            // and zero where a^b is (like `ops::ZeroMul`)
+            DynExpr::Powf(a, b) => Self::plus(
+                Self::times(
+                    DynExpr::Mul(
+                        boxed(b),
+                        Box::new(DynExpr::Powf(
+                            boxed(a),
+                            Box::new(DynExpr::Sub(boxed(b), Box::new(lit(1)))),
+                        )),
+                    ),
+                    a.derivative(x),
+                ),
+                Self::times(
+                    b.derivative(x),
+                    DynExpr::ZeroMul(
+                        Box::new(self.clone()),
+                        Box::new(DynExpr::Call(Function::Ln, boxed(a))),
+                    ),
+                ),
+            ),
+            // n a^(n - 1) a', like `float::powi_slope`
+            DynExpr::Powi(a, n) => Self::times(
+                Self::times(lit(*n), Self::power((**a).clone(), i64::from(*n) - 1)),
+                a.derivative(x),
+            ),
+            // (b a' - a b') / (a^2 + b^2)
+            DynExpr::Atan2(a, b) => Self::over(
+                Self::minus(
+                    Self::times((**b).clone(), a.derivative(x)),
+                    Self::times((**a).clone(), b.derivative(x)),
+                ),
+                DynExpr::Add(
+                    Box::new(DynExpr::Mul(boxed(a), boxed(a))),
+                    Box::new(DynExpr::Mul(boxed(b), boxed(b))),
+                ),
+            ),
+            DynExpr::Sum(terms) => Self::total(terms.iter().map(|t| t.derivative(x)).collect()),
+            // Generalized product rule, in one node
+            DynExpr::Product(factors) => Self::rule(
+                factors.clone(),
+                factors.iter().map(|f| f.derivative(x)).collect(),
+            ),
+            // (Σ g_i Π_{j≠i} f_j)' = Σ g_i' Π_{j≠i} f_j + Σ g_i (Σ_{j≠i} f_j' Π_{k≠i,j} f_k):
+            // the second term is a product rule over every factor but the i-th
+            DynExpr::ProductRule(factors, grads) => {
+                let df: Vec<Self> = factors.iter().map(|f| f.derivative(x)).collect();
+                let dg = grads.iter().map(|g| g.derivative(x)).collect();
+                let without = |v: &[Self], i: usize| {
+                    let (before, after) = v.split_at(i);
+                    before.iter().chain(&after[1..]).cloned().collect()
+                };
+                let cross = grads.iter().enumerate().map(|(i, g)| {
+                    Self::times(g.clone(), Self::rule(without(factors, i), without(&df, i)))
+                });
+                Self::total(
+                    core::iter::once(Self::rule(factors.clone(), dg))
+                        .chain(cross)
+                        .collect(),
+                )
+            }
+            DynExpr::Call(f, a) => {
+                let da = a.derivative(x);
+                let call = |f: Function| DynExpr::Call(f, boxed(a));
+                let square = |e: Self| DynExpr::Mul(Box::new(e.clone()), Box::new(e));
+                // sqrt(1 - a^2)
+                let root = || {
+                    DynExpr::Call(
+                        Function::Sqrt,
+                        Box::new(DynExpr::Sub(
+                            Box::new(lit(1)),
+                            Box::new(square((**a).clone())),
+                        )),
+                    )
+                };
+                match f {
+                    Function::Exp => Self::times(call(Function::Exp), da),
+                    Function::Ln => Self::over(da, (**a).clone()),
+                    Function::Sqrt => Self::over(
+                        da,
+                        DynExpr::Mul(Box::new(lit(2)), Box::new(call(Function::Sqrt))),
+                    ),
+                    Function::Sin => Self::times(call(Function::Cos), da),
+                    Function::Cos => Self::negate(Self::times(call(Function::Sin), da)),
+                    Function::Tan => Self::over(da, square(call(Function::Cos))),
+                    Function::Asin => Self::over(da, root()),
+                    Function::Acos => Self::negate(Self::over(da, root())),
+                    Function::Atan => Self::over(
+                        da,
+                        DynExpr::Add(Box::new(lit(1)), Box::new(square((**a).clone()))),
+                    ),
+                    Function::Sinh => Self::times(call(Function::Cosh), da),
+                    Function::Cosh => Self::times(call(Function::Sinh), da),
+                    Function::Tanh => Self::over(da, square(call(Function::Cosh))),
+                }
+            }
+        }
+    }
+}
+
+crate::implement_eval!(runtime 'a, T => DynExpr<'a, T> >-> T where { T: Real }: |self| self.value());
+crate::implement_grad!(runtime 'a, T => DynExpr<'a, T> >-> DynExpr<'a, T> where { T: Real }: |self, x| self.derivative(x));
+impl<T: Real> Dynamic for DynExpr<'_, T> {}
+
+impl<T: Real + Display> Node for DynExpr<'_, T> {
+    fn shape(&self) -> Shape<'_> {
+        match self {
+            DynExpr::Var(x, id) => Shape::Var {
+                ptr: *x as *const T as *const (),
+                id: *id,
+                value: *x,
+            },
+            DynExpr::Lit(c) => Shape::Const(c),
+            DynExpr::Neg(a) => Shape::Unary(Unary::Neg, &**a),
+            DynExpr::Add(a, b) => Shape::Binary(Binary::Add, &**a, &**b),
+            DynExpr::Sub(a, b) => Shape::Binary(Binary::Sub, &**a, &**b),
+            DynExpr::Mul(a, b) | DynExpr::ZeroMul(a, b) => Shape::Binary(Binary::Mul, &**a, &**b),
+            DynExpr::Div(a, b) => Shape::Binary(Binary::Div, &**a, &**b),
+            DynExpr::Rem(a, b) => Shape::Binary(Binary::Rem, &**a, &**b),
+            DynExpr::Powf(a, b) => Shape::Binary(Binary::Powf, &**a, &**b),
+            DynExpr::Powi(a, n) => Shape::Powi(&**a, *n),
+            DynExpr::Atan2(a, b) => Shape::Binary(Binary::Atan2, &**a, &**b),
+            DynExpr::Call(f, a) => Shape::Unary(f.unary(), &**a),
+            DynExpr::Sum(_) | DynExpr::ProductRule(..) => Shape::Nary(Binary::Add, self),
+            DynExpr::Product(_) => Shape::Nary(Binary::Mul, self),
+        }
+    }
+    #[inline]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.value().fmt(f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+}
+
+// Terms of a `Sum`, factors of a `Product`, or terms of a `ProductRule` (each a `Term` view); nothing otherwise
+impl<T: Real + Display> Operands for DynExpr<'_, T> {
+    #[inline]
+    fn try_for_each(&self, f: &mut dyn FnMut(&dyn Node) -> fmt::Result) -> fmt::Result {
+        match self {
+            DynExpr::Sum(xs) | DynExpr::Product(xs) => xs.iter().try_for_each(|x| f(x)),
+            // Terms with a zero gradient vanish (like `Self::times` would fold them)
+            DynExpr::ProductRule(factors, grads) => (0..grads.len())
+                .filter(|&i| !grads[i].is_zero())
+                .try_for_each(|i| f(&Term { factors, grads, i })),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Term `i` of a `ProductRule`: the gradient of factor `i` (unless it's one) times every other factor.
+struct Term<'r, 'a, T> {
+    factors: &'r [DynExpr<'a, T>],
+    grads: &'r [DynExpr<'a, T>],
+    i: usize,
+}
+
+impl<T: Real + Display> Operands for Term<'_, '_, T> {
+    #[inline]
+    fn try_for_each(&self, f: &mut dyn FnMut(&dyn Node) -> fmt::Result) -> fmt::Result {
+        if !self.grads[self.i].is_one() {
+            f(&self.grads[self.i])?;
+        }
+        let (before, after) = self.factors.split_at(self.i);
+        before.iter().chain(&after[1..]).try_for_each(|x| f(x))
+    }
+}
+
+impl<T: Real + Display> Node for Term<'_, '_, T> {
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Nary(Binary::Mul, self)
+    }
+    #[inline]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (before, after) = self.factors.split_at(self.i);
+        let others = before.iter().chain(&after[1..]);
+        others
+            .fold(self.grads[self.i].value(), |product, x| product * x.value())
+            .fmt(f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+}
+
+impl<T: Real + Display> Display for DynExpr<'_, T> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.named(&()).fmt(f)
+    }
+}
+
+impl<T: Real + Display> DynExpr<'_, T> {
+    /// Display with variables named (see `Expr::named`).
+    #[inline(always)]
+    pub fn named<'n, N: Names + ?Sized>(&'n self, names: &'n N) -> Named<'n, Self, N> {
+        Named { expr: self, names }
+    }
+}
+
+/// Conversion of any static expression (or node) into a `DynExpr`.
+pub trait IntoDyn<'a, T> {
+    /// Convert, allocating every node.
+    fn into_dyn(self) -> DynExpr<'a, T>;
+    /// Convert, then fold identities and constants by their runtime values (see `DynExpr::simplify`),
+    /// e.g. the `x * 1` and `x + 0` that `grad` leaves behind.
+    #[inline(always)]
+    fn simplify_dyn(self) -> DynExpr<'a, T>
+    where
+        Self: Sized,
+        T: Real,
+    {
+        self.into_dyn().simplify()
+    }
+}
+
+impl<'a, T: Real, E: IntoDyn<'a, T>> From<Expr<E>> for DynExpr<'a, T>
+where
+    E: Eval,
+{
+    #[inline(always)]
+    fn from(expr: Expr<E>) -> Self {
+        expr.0.into_dyn()
+    }
+}
+
+impl<'a, T> IntoDyn<'a, T> for DynExpr<'a, T> {
+    #[inline(always)]
+    fn into_dyn(self) -> Self {
+        self
+    }
+}
+
+impl<'a, T: Real, E: Eval + IntoDyn<'a, T>> IntoDyn<'a, T> for Expr<E> {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        self.0.into_dyn()
+    }
+}
+
+impl<'a, T: Real> IntoDyn<'a, T> for &'a T {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        DynExpr::Var(self, None)
+    }
+}
+
+impl<'a, T: Real, const ID: usize> IntoDyn<'a, T> for Var<ID, &'a T> {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        DynExpr::Var(self.0, Some(ID))
+    }
+}
+
+impl<'a, T: Real> IntoDyn<'a, T> for Lit<T> {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        DynExpr::Lit(self.0)
+    }
+}
+
+impl<'a, T: Real> IntoDyn<'a, T> for Delta<T> {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        DynExpr::Lit(if self.0 { *T::UNIT } else { *T::ZERO })
+    }
+}
+
+impl<'a, T: Real> IntoDyn<'a, T> for Zero<T> {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        DynExpr::Lit(*T::ZERO)
+    }
+}
+
+impl<'a, T: Real> IntoDyn<'a, T> for One<T> {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        DynExpr::Lit(*T::UNIT)
+    }
+}
+
+impl<'a, T: Real, A: Eval + IntoDyn<'a, T>> IntoDyn<'a, T> for ops::Neg<A> {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        DynExpr::Neg(Box::new(self.0.into_dyn()))
+    }
+}
+
+impl<'a, T: Real, A: Eval + IntoDyn<'a, T>> IntoDyn<'a, T> for ops::Powi<A> {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        DynExpr::Powi(Box::new(self.0.into_dyn()), self.1)
+    }
+}
+
+impl<'a, T: Real, A: Eval + IntoDyn<'a, T>> IntoDyn<'a, T> for ops::Monomial<A, T> {
+    #[inline(always)]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        DynExpr::Mul(
+            Box::new(DynExpr::Lit(self.0)),
+            Box::new(DynExpr::power(self.1.into_dyn(), self.2)),
+        )
+    }
+}
+
+macro_rules! into_dyn_binary {
+    ($($Name:ident),+) => {
+        $(
+            impl<'a, T: Real, A: Eval + IntoDyn<'a, T>, B: Eval + IntoDyn<'a, T>> IntoDyn<'a, T> for ops::$Name<A, B> {
+                #[inline(always)]
+                fn into_dyn(self) -> DynExpr<'a, T> {
+                    DynExpr::$Name(Box::new(self.0.into_dyn()), Box::new(self.1.into_dyn()))
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! into_dyn_call {
+    ($($Name:ident),+) => {
+        $(
+            impl<'a, T: Real, A: Eval + IntoDyn<'a, T>> IntoDyn<'a, T> for ops::$Name<A> {
+                #[inline(always)]
+                fn into_dyn(self) -> DynExpr<'a, T> {
+                    DynExpr::Call(Function::$Name, Box::new(self.0.into_dyn()))
+                }
+            }
+        )+
+    };
+}
+
+into_dyn_binary!(Add, Sub, Mul, ZeroMul, Div, Rem, Powf, Atan2);
+into_dyn_call!(Exp, Ln, Sqrt, Sin, Cos, Tan, Asin, Acos, Atan, Sinh, Cosh, Tanh);
+
+impl<'a, T: Real, E: Eval + IntoDyn<'a, T>, const N: usize> IntoDyn<'a, T> for ops::Sum<E, N> {
+    #[inline]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        let terms = self.0.into_iter().zip(self.1);
+        DynExpr::Sum(
+            terms
+                .filter_map(|(t, keep)| keep.then(|| t.into_dyn()))
+                .collect(),
+        )
+    }
+}
+
+impl<'a, T: Real, E: Eval + IntoDyn<'a, T>, const N: usize> IntoDyn<'a, T> for ops::Product<E, N> {
+    #[inline]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        let factors = self.0.into_iter().zip(self.1);
+        DynExpr::Product(
+            factors
+                .filter_map(|(f, keep)| keep.then(|| f.into_dyn()))
+                .collect(),
+        )
+    }
+}
+
+impl<'a, T: Real, E: Eval + IntoDyn<'a, T>, D: Eval + IntoDyn<'a, T>, const N: usize> IntoDyn<'a, T>
+    for ops::ProductRule<E, D, N>
+{
+    #[inline]
+    fn into_dyn(self) -> DynExpr<'a, T> {
+        let (factors, grads) = self
+            .0
+            .into_iter()
+            .zip(self.1)
+            .zip(self.2)
+            .filter_map(|(pair, keep)| keep.then_some(pair))
+            .map(|(f, g)| (f.into_dyn(), g.into_dyn()))
+            .unzip();
+        DynExpr::ProductRule(factors, grads)
+    }
+}
+
+/// Sum of any number of terms, of any types (e.g. `sum(xs.iter())` over variables), as one flat node.
+/// Also what `Iterator::sum` builds (e.g. `terms.into_iter().sum::<DynExpr<_>>()`).
+#[inline]
+pub fn sum<'a, T: Real, I: IntoIterator<Item: IntoDyn<'a, T>>>(terms: I) -> DynExpr<'a, T> {
+    DynExpr::Sum(terms.into_iter().map(IntoDyn::into_dyn).collect())
+}
+
+/// Product of any number of factors, of any types (e.g. `product(xs.iter())` over variables), as one flat node.
+/// Also what `Iterator::product` builds.
+#[inline]
+pub fn product<'a, T: Real, I: IntoIterator<Item: IntoDyn<'a, T>>>(factors: I) -> DynExpr<'a, T> {
+    DynExpr::Product(factors.into_iter().map(IntoDyn::into_dyn).collect())
+}
+
+impl<'a, T: Real, E: IntoDyn<'a, T>> core::iter::Sum<E> for DynExpr<'a, T> {
+    #[inline(always)]
+    fn sum<I: Iterator<Item = E>>(iter: I) -> Self {
+        sum(iter)
+    }
+}
+
+impl<'a, T: Real, E: IntoDyn<'a, T>> core::iter::Product<E> for DynExpr<'a, T> {
+    #[inline(always)]
+    fn product<I: Iterator<Item = E>>(iter: I) -> Self {
+        product(iter)
+    }
+}
+
+impl<'a, T: Real> Neg for DynExpr<'a, T> {
+    type Output = Self;
+    #[inline(always)]
+    fn neg(self) -> Self {
+        DynExpr::Neg(Box::new(self))
+    }
+}
+
+macro_rules! dyn_operator {
+    ($($Name:ident::$name:ident),+) => {
+        $(
+            impl<'a, T: Real, R: IntoDyn<'a, T>> $Name<R> for DynExpr<'a, T> {
+                type Output = Self;
+                #[inline(always)]
+                fn $name(self, r: R) -> Self {
+                    DynExpr::$Name(Box::new(self), Box::new(r.into_dyn()))
+                }
+            }
+            // A static expression on the left converts too, rather than building a static node around a `DynExpr`
+            impl<'a, T: Real, L: Eval + IntoDyn<'a, T>> $Name<DynExpr<'a, T>> for Expr<L> {
+                type Output = DynExpr<'a, T>;
+                #[inline(always)]
+                fn $name(self, r: DynExpr<'a, T>) -> DynExpr<'a, T> {
+                    DynExpr::$Name(Box::new(self.into_dyn()), Box::new(r))
+                }
+            }
+        )+
+    };
+}
+
+dyn_operator!(Add::add, Sub::sub, Mul::mul, Div::div, Rem::rem);
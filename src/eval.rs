@@ -39,6 +39,44 @@ pub trait Eval: ~const Own + ~const Ref {}
 /// Automagically implement `Eval`.
 #[macro_export]
 macro_rules! implement_eval {
+    // Operations that can't be evaluated at compile time (e.g. `f64::exp`)
+    (runtime $($l:lifetime,)* $($t:ident),+ $(; const $c:ident: $ct:ty)* => $name:ty >-> $output:ty where { $($bounds:tt)* }: |$self:ident| $body:expr) => {
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> $crate::eval::Typed for $name where $($bounds)* {
+            type Evaluated = $output;
+        }
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> $crate::eval::Own for $name where $($bounds)* {
+            #[inline(always)]
+            fn eval($self) -> $output {
+                $body
+            }
+        }
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> $crate::eval::Ref for $name where $($bounds)* {
+            #[inline(always)]
+            fn eval(&$self) -> $output {
+                $body
+            }
+        }
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> $crate::eval::Eval for $name where $($bounds)* {}
+    };
+    // Bounds with `+` can't be passed as `:path`, so take arbitrary `where` clauses instead
+    ($($l:lifetime,)* $($t:ident),+ $(; const $c:ident: $ct:ty)* => $name:ty >-> $output:ty where { $($bounds:tt)* }: |$self:ident| $body:expr) => {
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> const $crate::eval::Typed for $name where $($bounds)* {
+            type Evaluated = $output;
+        }
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> const $crate::eval::Own for $name where $($bounds)* {
+            #[inline(always)]
+            fn eval($self) -> $output {
+                $body
+            }
+        }
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> const $crate::eval::Ref for $name where $($bounds)* {
+            #[inline(always)]
+            fn eval(&$self) -> $output {
+                $body
+            }
+        }
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> const $crate::eval::Eval for $name where $($bounds)* {}
+    };
     ($name:ty >-> $output:ty: |$self:ident| $body:expr) => {
         impl const $crate::eval::Typed for $name {
             type Evaluated = $output;
@@ -1,16 +1,85 @@
 //! Expression template! Here it is!
 
-use crate::{eval::Eval, grad::Grad, leaf::Leaf};
+use crate::{
+    eval::Eval,
+    grad::{self, Grad, Id},
+    leaf::{Leaf, Lit, One, Var, Zero},
+    ops::{Product, Sum},
+};
+use core::marker::PhantomData;
 
 /// Expression template! Here it is!
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct Expr<T: ~const Eval>(pub(crate) T);
 
 crate::implement_eval!(T: Eval => Expr<T> >-> T::Evaluated: |self| where own { self.0.eval() } else { (&self.0).eval() });
-crate::implement_grad!(T: Grad => Expr<T> >-> Expr<T::Differentiated>: |self, x| where own { Expr(self.0.grad(x)) } else { Expr((&self.0).grad(x)) });
+impl<T: Eval> crate::ops::collapse::Dynamic for Expr<T> {}
+crate::implement_grad!(T: Grad<W> => Expr<T> >-> Expr<T::Differentiated>: |self, x| where own { Expr(self.0.grad(x)) } else { Expr((&self.0).grad(x)) });
+
+impl<T: ~const Eval> Expr<T> {
+    /// Differentiate with respect to the variable tagged `ID` (see `var_id`), even at compile time.
+    /// Whether each variable is the one tagged `ID` is known statically; with `id-folding`, constant gradients fold away at the type level.
+    #[inline(always)]
+    pub const fn grad_id<const ID: usize>(&self) -> Expr<<T as grad::Typed<Id<ID>>>::Differentiated>
+    where
+        T: ~const grad::Ref<Id<ID>>,
+    {
+        Expr(grad::Ref::grad(&self.0, Id))
+    }
+}
 
 /// Treat this reference as the basis for a `dxpr` tree rather than as a Rust value to eagerly evaluate.
 #[inline(always)]
 pub const fn var<T: ~const Leaf>(x: &T) -> Expr<&T> {
     Expr(x)
 }
+
+/// Like `var`, but tagged with an ID known at compile time (e.g. `var_id::<0, _>(&x)`), so `grad_id::<ID>()` works even in a `const`.
+#[inline(always)]
+pub const fn var_id<const ID: usize, T: ~const Leaf>(x: &T) -> Expr<Var<ID, &T>> {
+    Expr(Var(x))
+}
+
+/// Treat this value as a constant in a `dxpr` tree: evaluates to itself and differentiates to `Leaf::ZERO`.
+#[inline(always)]
+pub const fn lit<T: ~const Leaf + Copy>(x: T) -> Expr<Lit<T>> {
+    Expr(Lit(x))
+}
+
+/// Statically known zero: `Leaf::ZERO` as far as evaluation goes, but vanishes from gradients at the type level.
+#[inline(always)]
+pub const fn zero<T: ~const Leaf>() -> Expr<Zero<T>> {
+    Expr(Zero(PhantomData))
+}
+
+/// Statically known one: `Leaf::UNIT` as far as evaluation goes, but vanishes from products at the type level.
+#[inline(always)]
+pub const fn one<T: ~const Leaf>() -> Expr<One<T>> {
+    Expr(One(PhantomData))
+}
+
+/// Sum of terms of one type (e.g. `sum([var(&x), var(&y), var(&z)])`) as one flat node, however many there are,
+/// rather than the nest of `Add`s that chaining `+` builds. For terms of different types, see `dynamic::sum`.
+///
+/// `Expr` doesn't implement `Iterator::sum`: the number of terms is part of the node's type, which an iterator can't know.
+/// With `alloc`, an iterator of `DynExpr`s sums with `Iterator::sum` (or `dynamic::sum`) instead.
+#[inline(always)]
+pub fn sum<T: Eval, const N: usize>(terms: [Expr<T>; N]) -> Expr<Sum<T, N>>
+where
+    Sum<T, N>: Eval,
+{
+    Expr(Sum(terms.map(|t| t.0), [true; N]))
+}
+
+/// Product of factors of one type (e.g. `product([var(&x), var(&y), var(&z)])`) as one flat node, however many there are,
+/// rather than the nest of `Mul`s that chaining `*` builds. For factors of different types, see `dynamic::product`.
+/// Its gradient is one `ops::ProductRule` node, built and evaluated in time linear in the number of factors.
+///
+/// As with `sum`, `Expr` doesn't implement `Iterator::product`; `DynExpr` does.
+#[inline(always)]
+pub fn product<T: Eval, const N: usize>(factors: [Expr<T>; N]) -> Expr<Product<T, N>>
+where
+    Product<T, N>: Eval,
+{
+    Expr(Product(factors.map(|t| t.0), [true; N]))
+}
@@ -0,0 +1,144 @@
+//! Floating-point functions missing from `core::ops` (e.g. `exp` and `sin`), as used by `ops::{Exp, Sin, ...}`.
+//!
+//! Implemented for `f32` and `f64` (and references to them) only with the `std` feature,
+//! since `core` doesn't provide any of these functions; without `std`, implement `Float` yourself (e.g. with `libm`).
+
+use crate::leaf::Leaf;
+use core::ops::{Add, Mul};
+
+/// Floating-point functions missing from `core::ops`.
+/// Implemented for both values and references (like `core::ops`), so leaves (which evaluate to `&T`) work too.
+pub trait Float: Copy {
+    /// Owned type of the result (e.g. `f64` for both `f64` and `&f64`).
+    type Value: Float<Value = Self::Value> + Leaf + Copy;
+    /// Dereference (if necessary).
+    fn value(self) -> Self::Value;
+    /// Convert a (small) integer, e.g. the `2` in `d/dx sqrt(x) = 1 / (2 sqrt(x))`.
+    fn from_i32(n: i32) -> Self::Value;
+    /// `e^self`.
+    fn exp(self) -> Self::Value;
+    /// Natural logarithm.
+    fn ln(self) -> Self::Value;
+    /// Square root.
+    fn sqrt(self) -> Self::Value;
+    /// `self^n` for floating-point `n`.
+    fn powf(self, n: Self::Value) -> Self::Value;
+    /// `self^n` for integral `n`.
+    fn powi(self, n: i32) -> Self::Value;
+    /// Sine (in radians).
+    fn sin(self) -> Self::Value;
+    /// Cosine (in radians).
+    fn cos(self) -> Self::Value;
+    /// Tangent (in radians).
+    fn tan(self) -> Self::Value;
+    /// Inverse sine (in radians).
+    fn asin(self) -> Self::Value;
+    /// Inverse cosine (in radians).
+    fn acos(self) -> Self::Value;
+    /// Inverse tangent (in radians).
+    fn atan(self) -> Self::Value;
+    /// Four-quadrant inverse tangent of `self / x` (in radians).
+    fn atan2(self, x: Self::Value) -> Self::Value;
+    /// Hyperbolic sine.
+    fn sinh(self) -> Self::Value;
+    /// Hyperbolic cosine.
+    fn cosh(self) -> Self::Value;
+    /// Hyperbolic tangent.
+    fn tanh(self) -> Self::Value;
+}
+
+#[cfg(feature = "std")]
+macro_rules! implement_float {
+    ($($t:ty),+) => {
+        $(
+            implement_float!(@ $t, $t, |x| x);
+            implement_float!(@ &$t, $t, |x| *x);
+        )+
+    };
+    (@ $t:ty, $v:ty, |$x:ident| $value:expr) => {
+        impl Float for $t {
+            type Value = $v;
+            #[inline(always)]
+            fn value(self) -> $v {
+                let $x = self;
+                $value
+            }
+            #[inline(always)]
+            fn from_i32(n: i32) -> $v {
+                n as $v
+            }
+            #[inline(always)]
+            fn powf(self, n: $v) -> $v {
+                self.value().powf(n)
+            }
+            #[inline(always)]
+            fn powi(self, n: i32) -> $v {
+                self.value().powi(n)
+            }
+            #[inline(always)]
+            fn atan2(self, x: $v) -> $v {
+                self.value().atan2(x)
+            }
+            implement_float!(@ $v: exp, ln, sqrt, sin, cos, tan, asin, acos, atan, sinh, cosh, tanh);
+        }
+    };
+    (@ $v:ty: $($f:ident),+) => {
+        $(
+            #[inline(always)]
+            fn $f(self) -> $v {
+                self.value().$f()
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "std")]
+implement_float!(f32, f64);
+
+/// Split an exponent that may not fit an `i32` (e.g. `i32::MIN - 1`, from differentiating `powi(i32::MIN)`)
+/// into one or two that do, whose powers multiply to the original: `u^n = u^a u^b`.
+/// Splitting instead of dividing by `u` keeps `u = 0` exact. Covers exponents within twice the range of an `i32`.
+#[inline]
+pub(crate) const fn split_exponent(n: i64) -> (i32, Option<i32>) {
+    if n < i32::MIN as i64 {
+        (i32::MIN, Some((n - i32::MIN as i64) as i32))
+    } else if n > i32::MAX as i64 {
+        (i32::MAX, Some((n - i32::MAX as i64) as i32))
+    } else {
+        (n as i32, None)
+    }
+}
+
+/// `u^n` for an exponent that may not fit an `i32` (see `split_exponent`).
+#[inline]
+pub fn powi_wide<T: Float>(u: T, n: i64) -> T::Value
+where
+    T::Value: Mul<Output = T::Value>,
+{
+    match split_exponent(n) {
+        (a, None) => u.powi(a),
+        (a, Some(b)) => u.powi(a) * u.powi(b),
+    }
+}
+
+/// `n u^(n - 1)`, the derivative of `u^n`, shared by every mode of differentiation.
+/// `n - 1` is computed in an `i64`, since it doesn't fit an `i32` for `n = i32::MIN`.
+#[inline]
+pub fn powi_slope<T: Float>(u: T, n: i32) -> T::Value
+where
+    T::Value: Mul<Output = T::Value>,
+{
+    T::from_i32(n) * powi_wide(u, i64::from(n) - 1)
+}
+
+/// Convert an exponent that may not fit an `i32` (see `split_exponent`), e.g. the coefficient `n` in `(u^n)' = n u^(n - 1)`.
+#[inline]
+pub(crate) fn from_i64<T: Float>(n: i64) -> T::Value
+where
+    T::Value: Add<Output = T::Value>,
+{
+    match split_exponent(n) {
+        (a, None) => T::from_i32(a),
+        (a, Some(b)) => T::from_i32(a) + T::from_i32(b),
+    }
+}
@@ -0,0 +1,652 @@
+//! Forward-mode automatic differentation: one pass over an expression yields its value and its derivative together
+//! (e.g. `expr.eval_dual(&x)`), instead of building a whole new tree with `grad` and evaluating that separately.
+//! Works on the same trees as everything else, but computes on `Dual` numbers instead of plain values.
+
+use crate::{
+    eval::Eval,
+    expr::Expr,
+    float::Float,
+    grad::Wrt,
+    leaf::{Delta, Leaf, Lit, One, Var, Zero},
+    ops,
+};
+
+/// Value and derivative with respect to one variable, i.e. `value + deriv ε` with `ε^2 = 0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual<T> {
+    /// Primal value, exactly as `eval` would compute it.
+    pub value: T,
+    /// Derivative with respect to the variable passed to `eval_dual`.
+    pub deriv: T,
+}
+
+/// Evaluate an expression and its derivative in one pass, optionally at compile time (if evaluated into a `const`).
+/// `W` seeds each leaf's derivative: see `Seed`.
+#[const_trait]
+pub trait Forward<W>: ~const Eval {
+    /// Output of dual evaluation (e.g. `Dual<f64>`).
+    type Dual;
+    /// Evaluate along with the derivative with respect to `x` (a reference, a `grad::Id`, or a `Direction`).
+    fn eval_dual(&self, x: W) -> Self::Dual;
+}
+
+/// Derivative each leaf starts with: `Leaf::UNIT` for the variable we're differentiating with respect to
+/// (any `grad::Wrt`, i.e. a reference or an `Id`) or, for a directional derivative, its component of a `Direction`.
+#[const_trait]
+pub trait Seed<T>: Copy {
+    /// Derivative of the variable at `x` (tagged `id`, if any).
+    fn tangent(self, x: &T, id: Option<usize>) -> T;
+}
+
+impl<T: ~const Leaf<Unit = &'static T> + Copy + 'static, W: ~const Wrt> const Seed<T> for W {
+    #[inline(always)]
+    fn tangent(self, x: &T, id: Option<usize>) -> T {
+        let tagged = match id {
+            Some(id) => self.is_id(id),
+            None => false,
+        };
+        if tagged || self.is_ptr(x) {
+            *T::UNIT
+        } else {
+            *T::ZERO
+        }
+    }
+}
+
+/// Direction along which to differentiate: each variable's derivative starts as its component of `tangent`,
+/// so `eval_dual` computes a directional derivative (a Jacobian-vector product) in one pass.
+#[derive(Debug)]
+pub struct Direction<'a, T, const N: usize> {
+    vars: &'a [&'a T; N],
+    tangent: &'a [T; N],
+}
+
+impl<'a, T, const N: usize> Clone for Direction<'a, T, N> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T, const N: usize> Copy for Direction<'a, T, N> {}
+
+/// Differentiate along `tangent` (e.g. `expr.eval_dual(direction(&[&x, &y], &[1., -1.]))`).
+#[inline(always)]
+pub const fn direction<'a, T, const N: usize>(
+    vars: &'a [&'a T; N],
+    tangent: &'a [T; N],
+) -> Direction<'a, T, N> {
+    Direction { vars, tangent }
+}
+
+impl<'a, T: ~const Leaf<Unit = &'static T> + Copy + 'static, const N: usize> const Seed<T>
+    for Direction<'a, T, N>
+{
+    #[inline(always)]
+    fn tangent(self, x: &T, _id: Option<usize>) -> T {
+        let mut i = 0;
+        while i < N {
+            if self.vars[i].is_ptr(x) {
+                return self.tangent[i];
+            }
+            i += 1;
+        }
+        *T::ZERO
+    }
+}
+
+// Leaves
+
+impl<T: ~const Leaf<Unit = &'static T> + Copy + 'static, W: ~const Seed<T>> const Forward<W>
+    for &T
+{
+    type Dual = Dual<T>;
+    #[inline(always)]
+    fn eval_dual(&self, x: W) -> Dual<T> {
+        Dual {
+            value: **self,
+            deriv: x.tangent(*self, None),
+        }
+    }
+}
+
+impl<T: ~const Leaf<Unit = &'static T> + Copy + 'static, W: ~const Seed<T>, const ID: usize> const
+    Forward<W> for Var<ID, &T>
+{
+    type Dual = Dual<T>;
+    #[inline(always)]
+    fn eval_dual(&self, x: W) -> Dual<T> {
+        Dual {
+            value: *self.0,
+            deriv: x.tangent(self.0, Some(ID)),
+        }
+    }
+}
+
+impl<T: ~const Leaf<Unit = &'static T> + Copy + 'static, W: Copy> const Forward<W> for Lit<T> {
+    type Dual = Dual<T>;
+    #[inline(always)]
+    fn eval_dual(&self, _x: W) -> Dual<T> {
+        Dual {
+            value: self.0,
+            deriv: *T::ZERO,
+        }
+    }
+}
+
+impl<T: ~const Leaf<Unit = &'static T> + Copy + 'static, W: Copy> const Forward<W> for Delta<T> {
+    type Dual = Dual<T>;
+    #[inline(always)]
+    fn eval_dual(&self, _x: W) -> Dual<T> {
+        Dual {
+            value: if self.0 { *T::UNIT } else { *T::ZERO },
+            deriv: *T::ZERO,
+        }
+    }
+}
+
+impl<T: ~const Leaf<Unit = &'static T> + Copy + 'static, W: Copy> const Forward<W> for Zero<T> {
+    type Dual = Dual<T>;
+    #[inline(always)]
+    fn eval_dual(&self, _x: W) -> Dual<T> {
+        Dual {
+            value: *T::ZERO,
+            deriv: *T::ZERO,
+        }
+    }
+}
+
+impl<T: ~const Leaf<Unit = &'static T> + Copy + 'static, W: Copy> const Forward<W> for One<T> {
+    type Dual = Dual<T>;
+    #[inline(always)]
+    fn eval_dual(&self, _x: W) -> Dual<T> {
+        Dual {
+            value: *T::UNIT,
+            deriv: *T::ZERO,
+        }
+    }
+}
+
+impl<W: Copy, T: ~const Forward<W>> const Forward<W> for Expr<T> {
+    type Dual = T::Dual;
+    #[inline(always)]
+    fn eval_dual(&self, x: W) -> T::Dual {
+        self.0.eval_dual(x)
+    }
+}
+
+// Operations: dual arithmetic below already knows every rule, so each node just applies its own operator
+
+macro_rules! forward_ops {
+    (unary: $($Unary:ident: $unary:ident),+; binary: $($Binary:ident: $binary:ident),+) => {
+        $(
+            impl<W: Copy, T: ~const Forward<W, Dual: ~const core::ops::$Unary>> const Forward<W> for ops::$Unary<T>
+            where
+                Self: ~const Eval,
+            {
+                type Dual = <T::Dual as core::ops::$Unary>::Output;
+                #[inline(always)]
+                fn eval_dual(&self, x: W) -> Self::Dual {
+                    core::ops::$Unary::$unary(self.0.eval_dual(x))
+                }
+            }
+        )+
+        $(
+            impl<W: Copy, L: ~const Forward<W, Dual: ~const core::ops::$Binary<R::Dual>>, R: ~const Forward<W>> const
+                Forward<W> for ops::$Binary<L, R>
+            where
+                Self: ~const Eval,
+            {
+                type Dual = <L::Dual as core::ops::$Binary<R::Dual>>::Output;
+                #[inline(always)]
+                fn eval_dual(&self, x: W) -> Self::Dual {
+                    core::ops::$Binary::$binary(self.0.eval_dual(x), self.1.eval_dual(x))
+                }
+            }
+        )+
+    };
+}
+
+forward_ops!(
+    unary: Neg: neg, Not: not;
+    binary: Add: add, Sub: sub, Mul: mul, Div: div, Rem: rem, Shl: shl, Shr: shr,
+        BitAnd: bitand, BitOr: bitor, BitXor: bitxor
+);
+
+macro_rules! forward_fns {
+    ($($Name:ident: $name:ident),+) => {
+        $(
+            impl<W, T: Forward<W, Dual: Float>> Forward<W> for ops::$Name<T>
+            where
+                Self: Eval,
+            {
+                type Dual = <T::Dual as Float>::Value;
+                #[inline(always)]
+                fn eval_dual(&self, x: W) -> Self::Dual {
+                    self.0.eval_dual(x).$name()
+                }
+            }
+        )+
+    };
+}
+
+forward_fns!(
+    Exp: exp,
+    Ln: ln,
+    Sqrt: sqrt,
+    Sin: sin,
+    Cos: cos,
+    Tan: tan,
+    Asin: asin,
+    Acos: acos,
+    Atan: atan,
+    Sinh: sinh,
+    Cosh: cosh,
+    Tanh: tanh
+);
+
+impl<
+        W: Copy,
+        L: Forward<W, Dual: Float>,
+        R: Forward<W, Dual: Float<Value = <L::Dual as Float>::Value>>,
+    > Forward<W> for ops::Powf<L, R>
+where
+    Self: Eval,
+{
+    type Dual = <L::Dual as Float>::Value;
+    #[inline(always)]
+    fn eval_dual(&self, x: W) -> Self::Dual {
+        self.0.eval_dual(x).powf(self.1.eval_dual(x).value())
+    }
+}
+
+impl<
+        W: Copy,
+        L: Forward<W, Dual: Float>,
+        R: Forward<W, Dual: Float<Value = <L::Dual as Float>::Value>>,
+    > Forward<W> for ops::Atan2<L, R>
+where
+    Self: Eval,
+{
+    type Dual = <L::Dual as Float>::Value;
+    #[inline(always)]
+    fn eval_dual(&self, x: W) -> Self::Dual {
+        self.0.eval_dual(x).atan2(self.1.eval_dual(x).value())
+    }
+}
+
+impl<W, T: Forward<W, Dual: Float>> Forward<W> for ops::Powi<T>
+where
+    Self: Eval,
+{
+    type Dual = <T::Dual as Float>::Value;
+    #[inline(always)]
+    fn eval_dual(&self, x: W) -> Self::Dual {
+        self.0.eval_dual(x).powi(self.1)
+    }
+}
+
+impl<
+        W: Copy,
+        L: Forward<W, Dual = Dual<V>>,
+        R: Forward<W, Dual = Dual<V>>,
+        V: Leaf<Unit = &'static V> + Copy + PartialEq + 'static,
+    > Forward<W> for ops::ZeroMul<L, R>
+where
+    Self: Eval,
+    Dual<V>: core::ops::Mul<Output = Dual<V>>,
+{
+    type Dual = Dual<V>;
+    #[inline(always)]
+    fn eval_dual(&self, x: W) -> Dual<V> {
+        let (l, r) = (self.0.eval_dual(x), self.1.eval_dual(x));
+        let product = l * r;
+        Dual {
+            value: if l.value == *V::ZERO {
+                l.value
+            } else {
+                product.value
+            },
+            deriv: product.deriv,
+        }
+    }
+}
+
+impl<W: Copy, T: Forward<W, Dual = Dual<V>>, V: Leaf<Unit = &'static V> + Copy + 'static> Forward<W>
+    for ops::Monomial<T, V>
+where
+    Self: Eval,
+    Dual<V>: Float<Value = Dual<V>> + core::ops::Mul<Output = Dual<V>>,
+{
+    type Dual = Dual<V>;
+    #[inline(always)]
+    fn eval_dual(&self, x: W) -> Dual<V> {
+        Lit(self.0).eval_dual(x) * crate::float::powi_wide(self.1.eval_dual(x), self.2)
+    }
+}
+
+// Dual arithmetic
+
+impl<T: ~const core::ops::Neg<Output = T>> const core::ops::Neg for Dual<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn neg(self) -> Self {
+        Dual {
+            value: -self.value,
+            deriv: -self.deriv,
+        }
+    }
+}
+
+impl<T: ~const core::ops::Add<Output = T>> const core::ops::Add for Dual<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, r: Self) -> Self {
+        Dual {
+            value: self.value + r.value,
+            deriv: self.deriv + r.deriv,
+        }
+    }
+}
+
+impl<T: ~const core::ops::Sub<Output = T>> const core::ops::Sub for Dual<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, r: Self) -> Self {
+        Dual {
+            value: self.value - r.value,
+            deriv: self.deriv - r.deriv,
+        }
+    }
+}
+
+// Product rule: (uv)' = u'v + uv'
+impl<T: ~const core::ops::Mul<Output = T> + ~const core::ops::Add<Output = T> + Copy> const
+    core::ops::Mul for Dual<T>
+{
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, r: Self) -> Self {
+        Dual {
+            value: self.value * r.value,
+            deriv: self.deriv * r.value + self.value * r.deriv,
+        }
+    }
+}
+
+// Quotient rule: (u/v)' = (u'v - uv') / v^2
+impl<
+        T: ~const core::ops::Div<Output = T>
+            + ~const core::ops::Mul<Output = T>
+            + ~const core::ops::Sub<Output = T>
+            + Copy,
+    > const core::ops::Div for Dual<T>
+{
+    type Output = Self;
+    #[inline(always)]
+    fn div(self, r: Self) -> Self {
+        Dual {
+            value: self.value / r.value,
+            deriv: (self.deriv * r.value - self.value * r.deriv) / (r.value * r.value),
+        }
+    }
+}
+
+// Remainder: (a % b)' = a' - qb', where q = (a - a % b) / b (see `ops::Rem`)
+impl<
+        T: ~const core::ops::Rem<Output = T>
+            + ~const core::ops::Div<Output = T>
+            + ~const core::ops::Mul<Output = T>
+            + ~const core::ops::Sub<Output = T>
+            + Copy,
+    > const core::ops::Rem for Dual<T>
+{
+    type Output = Self;
+    #[inline(always)]
+    fn rem(self, r: Self) -> Self {
+        let value = self.value % r.value;
+        Dual {
+            value,
+            deriv: self.deriv - (self.value - value) / r.value * r.deriv,
+        }
+    }
+}
+
+// Shifts scale by a constant power of two (see `ops::Shl`), so the shift amount may have a different type
+impl<T: ~const core::ops::Shl<U, Output = T>, U: Copy> const core::ops::Shl<Dual<U>> for Dual<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn shl(self, r: Dual<U>) -> Self {
+        Dual {
+            value: self.value << r.value,
+            deriv: self.deriv << r.value,
+        }
+    }
+}
+
+impl<T: ~const core::ops::Shr<U, Output = T>, U: Copy> const core::ops::Shr<Dual<U>> for Dual<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn shr(self, r: Dual<U>) -> Self {
+        Dual {
+            value: self.value >> r.value,
+            deriv: self.deriv >> r.value,
+        }
+    }
+}
+
+// Boolean difference (see `ops::Not` & co.), which only holds over `bool`: d(!u) = du
+impl const core::ops::Not for Dual<bool> {
+    type Output = Self;
+    #[inline(always)]
+    fn not(self) -> Self {
+        Dual {
+            value: !self.value,
+            deriv: self.deriv,
+        }
+    }
+}
+
+// Boolean difference: d(u ^ v) = du ^ dv
+impl const core::ops::BitXor for Dual<bool> {
+    type Output = Self;
+    #[inline(always)]
+    fn bitxor(self, r: Self) -> Self {
+        Dual {
+            value: self.value ^ r.value,
+            deriv: self.deriv ^ r.deriv,
+        }
+    }
+}
+
+// Boolean difference: d(uv) = u dv ^ du v ^ du dv
+impl const core::ops::BitAnd for Dual<bool> {
+    type Output = Self;
+    #[inline(always)]
+    fn bitand(self, r: Self) -> Self {
+        Dual {
+            value: self.value & r.value,
+            deriv: (self.value & r.deriv) ^ (self.deriv & r.value) ^ (self.deriv & r.deriv),
+        }
+    }
+}
+
+// Boolean difference: d(u | v) = !u dv ^ du !v ^ du dv
+impl const core::ops::BitOr for Dual<bool> {
+    type Output = Self;
+    #[inline(always)]
+    fn bitor(self, r: Self) -> Self {
+        Dual {
+            value: self.value | r.value,
+            deriv: (!self.value & r.deriv) ^ (self.deriv & !r.value) ^ (self.deriv & r.deriv),
+        }
+    }
+}
+
+impl<T: ~const Leaf<Unit = &'static T> + Copy + 'static> const Leaf for Dual<T> {
+    type Unit = Self;
+    const UNIT: Self = Dual {
+        value: *T::UNIT,
+        deriv: *T::ZERO,
+    };
+    const ZERO: Self = Dual {
+        value: *T::ZERO,
+        deriv: *T::ZERO,
+    };
+}
+
+/// Constant: zero derivative.
+impl<T: Leaf<Unit = &'static T> + Copy + 'static> From<T> for Dual<T> {
+    #[inline(always)]
+    fn from(value: T) -> Self {
+        Dual {
+            value,
+            deriv: *T::ZERO,
+        }
+    }
+}
+
+// Chain rule for each function (compare `ops::Exp` & co.)
+impl<T> Float for Dual<T>
+where
+    T: Float<Value = T>
+        + Leaf<Unit = &'static T>
+        + 'static
+        + core::ops::Neg<Output = T>
+        + core::ops::Add<Output = T>
+        + core::ops::Sub<Output = T>
+        + core::ops::Mul<Output = T>
+        + core::ops::Div<Output = T>
+        + PartialEq,
+{
+    type Value = Self;
+    #[inline(always)]
+    fn value(self) -> Self {
+        self
+    }
+    #[inline(always)]
+    fn from_i32(n: i32) -> Self {
+        Dual {
+            value: T::from_i32(n),
+            deriv: *T::ZERO,
+        }
+    }
+    #[inline(always)]
+    fn exp(self) -> Self {
+        let value = self.value.exp();
+        Dual {
+            value,
+            deriv: value * self.deriv,
+        }
+    }
+    #[inline(always)]
+    fn ln(self) -> Self {
+        Dual {
+            value: self.value.ln(),
+            deriv: self.deriv / self.value,
+        }
+    }
+    #[inline(always)]
+    fn sqrt(self) -> Self {
+        let value = self.value.sqrt();
+        Dual {
+            value,
+            deriv: self.deriv / (value + value),
+        }
+    }
+    #[inline(always)]
+    fn powf(self, n: Self) -> Self {
+        let value = self.value.powf(n.value);
+        // Skip `ln u` unless the exponent actually varies, since e.g. `0^2` would otherwise have derivative `NaN`,
+        // and where the power is zero, since `0^v` is flat in `v` (like `ops::ZeroMul`)
+        let deriv = n.value * self.value.powf(n.value - T::from_i32(1)) * self.deriv;
+        Dual {
+            value,
+            deriv: if n.deriv == *T::ZERO || value == *T::ZERO {
+                deriv
+            } else {
+                deriv + value * self.value.ln() * n.deriv
+            },
+        }
+    }
+    #[inline(always)]
+    fn powi(self, n: i32) -> Self {
+        Dual {
+            value: self.value.powi(n),
+            deriv: crate::float::powi_slope(self.value, n) * self.deriv,
+        }
+    }
+    #[inline(always)]
+    fn sin(self) -> Self {
+        Dual {
+            value: self.value.sin(),
+            deriv: self.value.cos() * self.deriv,
+        }
+    }
+    #[inline(always)]
+    fn cos(self) -> Self {
+        Dual {
+            value: self.value.cos(),
+            deriv: -(self.value.sin() * self.deriv),
+        }
+    }
+    #[inline(always)]
+    fn tan(self) -> Self {
+        let cos = self.value.cos();
+        Dual {
+            value: self.value.tan(),
+            deriv: self.deriv / (cos * cos),
+        }
+    }
+    #[inline(always)]
+    fn asin(self) -> Self {
+        Dual {
+            value: self.value.asin(),
+            deriv: self.deriv / (T::from_i32(1) - self.value * self.value).sqrt(),
+        }
+    }
+    #[inline(always)]
+    fn acos(self) -> Self {
+        Dual {
+            value: self.value.acos(),
+            deriv: -(self.deriv / (T::from_i32(1) - self.value * self.value).sqrt()),
+        }
+    }
+    #[inline(always)]
+    fn atan(self) -> Self {
+        Dual {
+            value: self.value.atan(),
+            deriv: self.deriv / (T::from_i32(1) + self.value * self.value),
+        }
+    }
+    #[inline(always)]
+    fn atan2(self, x: Self) -> Self {
+        Dual {
+            value: self.value.atan2(x.value),
+            deriv: (x.value * self.deriv - self.value * x.deriv)
+                / (x.value * x.value + self.value * self.value),
+        }
+    }
+    #[inline(always)]
+    fn sinh(self) -> Self {
+        Dual {
+            value: self.value.sinh(),
+            deriv: self.value.cosh() * self.deriv,
+        }
+    }
+    #[inline(always)]
+    fn cosh(self) -> Self {
+        Dual {
+            value: self.value.cosh(),
+            deriv: self.value.sinh() * self.deriv,
+        }
+    }
+    #[inline(always)]
+    fn tanh(self) -> Self {
+        let cosh = self.value.cosh();
+        Dual {
+            value: self.value.tanh(),
+            deriv: self.deriv / (cosh * cosh),
+        }
+    }
+}
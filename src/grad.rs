@@ -11,89 +11,304 @@
 //!        *Eval
 //! ```
 
-use crate::eval::Eval;
+#[cfg(not(feature = "std"))]
+use crate::forward::Forward;
+use crate::{eval::Eval, forward::Dual};
+#[cfg(feature = "std")]
+use crate::{
+    forward::{direction, Direction},
+    leaf::Leaf,
+    reverse::Backward,
+};
 
-/// Output type.
+/// Output type, which can depend on the variable `W` (e.g. statically `One` or `Zero` for an `Id`).
 #[const_trait]
-pub trait Typed: ~const Eval {
+pub trait Typed<W>: ~const Eval {
     /// Output of evaluation (i.e. `grad(&x) -> ???`).
     type Differentiated: ~const Eval;
 }
 
 /// Implementation taking `self` (moved).
 #[const_trait]
-pub trait Own: ~const Typed {
+pub trait Own<W>: ~const Typed<W> {
     /// Fold an expression into a value.
-    fn grad<U>(self, x: &U) -> Self::Differentiated;
+    fn grad(self, x: W) -> Self::Differentiated;
 }
 
 /// Implementation taking `&self` (not moved).
 #[const_trait]
-pub trait Ref: ~const Typed {
+pub trait Ref<W>: ~const Typed<W> {
     /// Fold an expression into a value without consuming the expression.
-    fn grad<U>(&self, x: &U) -> Self::Differentiated;
+    fn grad(&self, x: W) -> Self::Differentiated;
 }
 
 /// Automatically differentiate an expression, optionally at compile time (if evaluated into a `const`).
 #[const_trait]
-pub trait Grad: ~const Own + ~const Ref {}
+pub trait Grad<W>: ~const Own<W> + ~const Ref<W> {}
+
+/// Variable with respect to which we differentiate: either a reference (compared by address) or an `Id`.
+#[const_trait]
+pub trait Wrt: Copy {
+    /// Whether this is the variable at `x`.
+    fn is_ptr<T>(self, x: &T) -> bool;
+    /// Whether this is the variable tagged `id`.
+    fn is_id(self, id: usize) -> bool;
+}
+
+impl<U> const Wrt for &U {
+    #[inline(always)]
+    fn is_ptr<T>(self, x: &T) -> bool {
+        match (self as *const U as *const T).guaranteed_eq(x as *const T) {
+            None => panic!("Couldn't tell whether two values were the same (this often happens at compile time and seems to be an issue with Rust itself)"),
+            Some(b) => b,
+        }
+    }
+    #[inline(always)]
+    fn is_id(self, _id: usize) -> bool {
+        false
+    }
+}
+
+/// Variable ID known at compile time: see `expr::var_id`.
+#[derive(Clone, Copy, Debug)]
+pub struct Id<const ID: usize>;
+
+impl<const ID: usize> const Wrt for Id<ID> {
+    #[inline(always)]
+    fn is_ptr<T>(self, _x: &T) -> bool {
+        false
+    }
+    #[inline(always)]
+    fn is_id(self, id: usize) -> bool {
+        id == ID
+    }
+}
+
+/// Differentiate with respect to several variables at once (e.g. `expr.grad_all((&a, &b))` or `expr.grad_array(&[&x, &y])`).
+/// Gradients with respect to variables of one type (e.g. references) all have the same type, so arrays of them are homogeneous.
+pub trait GradAll: Eval {
+    /// Gradient with respect to each variable in a tuple (references and `Id`s can be mixed), as a tuple of expressions.
+    #[inline(always)]
+    fn grad_all<V: Wrts<Self>>(&self, vars: V) -> V::Grads {
+        vars.grads(self)
+    }
+    /// Gradient with respect to each variable in an array, as an array of expressions.
+    #[inline(always)]
+    fn grad_array<W: Wrt, const N: usize>(
+        &self,
+        vars: &[W; N],
+    ) -> [<Self as Typed<W>>::Differentiated; N]
+    where
+        Self: Ref<W>,
+    {
+        core::array::from_fn(|i| Ref::grad(self, vars[i]))
+    }
+}
+
+impl<T: Eval> GradAll for T {}
+
+/// Tuple of variables to differentiate with respect to (see `GradAll::grad_all`).
+pub trait Wrts<T> {
+    /// Tuple of gradients, one per variable.
+    type Grads;
+    /// Differentiate `expr` with respect to each variable.
+    fn grads(self, expr: &T) -> Self::Grads;
+}
+
+macro_rules! implement_wrts {
+    ($($W:ident: $i:tt),+) => {
+        impl<T: $(Ref<$W> +)+, $($W: Wrt),+> Wrts<T> for ($($W,)+) {
+            type Grads = ($(<T as Typed<$W>>::Differentiated,)+);
+            #[inline(always)]
+            fn grads(self, expr: &T) -> Self::Grads {
+                ($(Ref::grad(expr, self.$i),)+)
+            }
+        }
+    };
+}
+
+implement_wrts!(A: 0);
+implement_wrts!(A: 0, B: 1);
+implement_wrts!(A: 0, B: 1, C: 2);
+implement_wrts!(A: 0, B: 1, C: 2, D: 3);
+implement_wrts!(A: 0, B: 1, C: 2, D: 3, E: 4);
+implement_wrts!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+implement_wrts!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+implement_wrts!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Hessian (`H[i][j] = d^2 f / dx_i dx_j`), forward over reverse:
+/// one reverse pass over `Dual`s per variable `x_j`, each yielding every `d/dx_j (d f / dx_i)` at once.
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn hessian<S, E, const N: usize>(expr: &E, vars: &[&S; N]) -> [[S; N]; N]
+where
+    S: Leaf<Unit = &'static S> + Copy + 'static,
+    E: for<'x> Backward<&'x S, Value = Dual<S>>,
+    Dual<S>: Leaf<Unit = Dual<S>>,
+{
+    let columns: [_; N] = core::array::from_fn(|j| expr.backward_along(vars[j]));
+    core::array::from_fn(|i| core::array::from_fn(|j| columns[j].wrt_dual(vars[i]).deriv))
+}
+
+/// Hessian (`H[i][j] = d^2 f / dx_i dx_j`), forward mode over symbolic gradients (without `std`, so without reverse mode):
+/// differentiate symbolically once per variable, then take every second derivative of each gradient in forward mode,
+/// i.e. `N^2` forward passes over gradient expressions.
+#[cfg(not(feature = "std"))]
+#[inline(always)]
+pub fn hessian<'v, S, E: Ref<&'v S>, const N: usize>(expr: &E, vars: &[&'v S; N]) -> [[S; N]; N]
+where
+    E::Differentiated: for<'x> Forward<&'x S, Dual = Dual<S>>,
+{
+    let grads = expr.grad_array(vars);
+    core::array::from_fn(|i| core::array::from_fn(|j| grads[i].eval_dual(vars[j]).deriv))
+}
+
+/// Hessian-vector product (`(H v)[i] = sum_j d^2 f / dx_i dx_j v[j]`), forward over reverse:
+/// a single reverse pass over `Dual`s seeded along `v`, so it costs a small multiple of evaluating `expr` once,
+/// without ever building the Hessian (or any gradient expression).
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn hvp<S, E, const N: usize>(expr: &E, vars: &[&S; N], v: &[S; N]) -> [S; N]
+where
+    S: Leaf<Unit = &'static S> + Copy + 'static,
+    E: for<'x> Backward<Direction<'x, S, N>, Value = Dual<S>>,
+    Dual<S>: Leaf<Unit = Dual<S>>,
+{
+    let grads = expr.backward_along(direction(vars, v));
+    core::array::from_fn(|i| grads.wrt_dual(vars[i]).deriv)
+}
 
 /// Automagically implement `Grad`.
 #[macro_export]
 macro_rules! implement_grad {
+    // Leaves, whose gradients depend on the kind of variable `$w` (e.g. statically `One` or `Zero` for an `Id`)
+    ($($l:lifetime,)* $($t:ident),+ $(; const $c:ident: $ct:ty)* => $name:ty, wrt $w:ty >-> $output:ty where { $($bounds:tt)* }: |$self:ident, $x:ident| where own { $own:expr } else { $ref:expr }) => {
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> const $crate::grad::Typed<$w> for $name where $($bounds)* {
+            type Differentiated = $output;
+        }
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> const $crate::grad::Own<$w> for $name where $($bounds)* {
+            #[inline(always)]
+            fn grad($self, $x: $w) -> $output {
+                $own
+            }
+        }
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> const $crate::grad::Ref<$w> for $name where $($bounds)* {
+            #[inline(always)]
+            fn grad(&$self, $x: $w) -> $output {
+                $ref
+            }
+        }
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> const $crate::grad::Grad<$w> for $name where $($bounds)* {}
+    };
+    // Operations that can't be differentiated at compile time (e.g. `f64::exp`)
+    (runtime $($l:lifetime,)* $($t:ident),+ $(; const $c:ident: $ct:ty)* => $name:ty >-> $output:ty where { $($bounds:tt)* }: |$self:ident, $x:ident| $body:expr) => {
+        impl<$($l,)* W: $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> $crate::grad::Typed<W> for $name where $($bounds)* {
+            type Differentiated = $output;
+        }
+        impl<$($l,)* W: $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> $crate::grad::Own<W> for $name where $($bounds)* {
+            #[inline(always)]
+            fn grad($self, $x: W) -> $output {
+                $body
+            }
+        }
+        impl<$($l,)* W: $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> $crate::grad::Ref<W> for $name where $($bounds)* {
+            #[inline(always)]
+            fn grad(&$self, $x: W) -> $output {
+                $body
+            }
+        }
+        impl<$($l,)* W: $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> $crate::grad::Grad<W> for $name where $($bounds)* {}
+    };
+    // Bounds with `+` can't be passed as `:path`, so take arbitrary `where` clauses instead
+    ($($l:lifetime,)* $($t:ident),+ $(; const $c:ident: $ct:ty)* => $name:ty >-> $output:ty where { $($bounds:tt)* }: |$self:ident, $x:ident| where own { $own:expr } else { $ref:expr }) => {
+        impl<$($l,)* W: ~const $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> const $crate::grad::Typed<W> for $name where $($bounds)* {
+            type Differentiated = $output;
+        }
+        impl<$($l,)* W: ~const $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> const $crate::grad::Own<W> for $name where $($bounds)* {
+            #[inline(always)]
+            fn grad($self, $x: W) -> $output {
+                $own
+            }
+        }
+        impl<$($l,)* W: ~const $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> const $crate::grad::Ref<W> for $name where $($bounds)* {
+            #[inline(always)]
+            fn grad(&$self, $x: W) -> $output {
+                $ref
+            }
+        }
+        impl<$($l,)* W: ~const $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> const $crate::grad::Grad<W> for $name where $($bounds)* {}
+    };
+    ($($l:lifetime,)* $($t:ident),+ $(; const $c:ident: $ct:ty)* => $name:ty >-> $output:ty where { $($bounds:tt)* }: |$self:ident, $x:ident| $body:expr) => {
+        impl<$($l,)* W: ~const $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> const $crate::grad::Typed<W> for $name where $($bounds)* {
+            type Differentiated = $output;
+        }
+        impl<$($l,)* W: ~const $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> const $crate::grad::Own<W> for $name where $($bounds)* {
+            #[inline(always)]
+            fn grad($self, $x: W) -> $output {
+                $body
+            }
+        }
+        impl<$($l,)* W: ~const $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> const $crate::grad::Ref<W> for $name where $($bounds)* {
+            #[inline(always)]
+            fn grad(&$self, $x: W) -> $output {
+                $body
+            }
+        }
+        impl<$($l,)* W: ~const $crate::grad::Wrt, $($t,)+ $(const $c: $ct),*> const $crate::grad::Grad<W> for $name where $($bounds)* {}
+    };
     ($name:ty >-> $output:ty: |$self:ident, $x:ident| $body:expr) => {
-        impl const $crate::grad::Typed for $name {
+        impl<W: ~const $crate::grad::Wrt> const $crate::grad::Typed<W> for $name {
             type Differentiated = $output;
         }
-        impl const $crate::grad::Own for $name {
+        impl<W: ~const $crate::grad::Wrt> const $crate::grad::Own<W> for $name {
             #[inline(always)]
-            fn grad<U>($self, $x: &U) -> $output {
+            fn grad($self, $x: W) -> $output {
                 $body
             }
         }
-        impl const $crate::grad::Ref for $name {
+        impl<W: ~const $crate::grad::Wrt> const $crate::grad::Ref<W> for $name {
             #[inline(always)]
-            fn grad<U>(&$self, $x: &U) -> $output {
+            fn grad(&$self, $x: W) -> $output {
                 $body
             }
         }
-        impl const $crate::grad::Grad for $name {}
+        impl<W: ~const $crate::grad::Wrt> const $crate::grad::Grad<W> for $name {}
     };
     ($($t:ident: $const_trait:path),+ => $name:ty >-> $output:ty: |$self:ident, $x:ident| $body:expr) => {
-        impl<$($t: ~const $const_trait),+> const $crate::grad::Typed for $name {
+        impl<W: ~const $crate::grad::Wrt, $($t: ~const $const_trait),+> const $crate::grad::Typed<W> for $name {
             type Differentiated = $output;
         }
-        impl<$($t: ~const $const_trait),+> const $crate::grad::Own for $name {
+        impl<W: ~const $crate::grad::Wrt, $($t: ~const $const_trait),+> const $crate::grad::Own<W> for $name {
             #[inline(always)]
-            fn grad<U>($self, $x: &U) -> $output {
+            fn grad($self, $x: W) -> $output {
                 $body
             }
         }
-        impl<$($t: ~const $const_trait),+> const $crate::grad::Ref for $name {
+        impl<W: ~const $crate::grad::Wrt, $($t: ~const $const_trait),+> const $crate::grad::Ref<W> for $name {
             #[inline(always)]
-            fn grad<U>(&$self, $x: &U) -> $output {
+            fn grad(&$self, $x: W) -> $output {
                 $body
             }
         }
-        impl<$($t: ~const $const_trait),+> const $crate::grad::Grad for $name {}
+        impl<W: ~const $crate::grad::Wrt, $($t: ~const $const_trait),+> const $crate::grad::Grad<W> for $name {}
     };
     // It would be so nice to have `where` as a parallel to C++'s `if constexpr`...
     ($($t:ident: $const_trait:path),+ => $name:ty >-> $output:ty: |$self:ident, $x:ident| where own { $own:expr } else { $ref:expr }) => {
-        impl<$($t: ~const $const_trait),+> const $crate::grad::Typed for $name {
+        impl<W: ~const $crate::grad::Wrt, $($t: ~const $const_trait),+> const $crate::grad::Typed<W> for $name {
             type Differentiated = $output;
         }
-        impl<$($t: ~const $const_trait),+> const $crate::grad::Own for $name {
+        impl<W: ~const $crate::grad::Wrt, $($t: ~const $const_trait),+> const $crate::grad::Own<W> for $name {
             #[inline(always)]
-            fn grad<U>($self, $x: &U) -> $output {
+            fn grad($self, $x: W) -> $output {
                 $own
             }
         }
-        impl<$($t: ~const $const_trait),+> const $crate::grad::Ref for $name {
+        impl<W: ~const $crate::grad::Wrt, $($t: ~const $const_trait),+> const $crate::grad::Ref<W> for $name {
             #[inline(always)]
-            fn grad<U>(&$self, $x: &U) -> $output {
+            fn grad(&$self, $x: W) -> $output {
                 $ref
             }
         }
-        impl<$($t: ~const $const_trait),+> const $crate::grad::Grad for $name {}
+        impl<W: ~const $crate::grad::Wrt, $($t: ~const $const_trait),+> const $crate::grad::Grad<W> for $name {}
     };
 }
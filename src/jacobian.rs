@@ -0,0 +1,186 @@
+//! Vector-valued functions (arrays or tuples of expressions) and their Jacobians,
+//! e.g. `jacobian(&(f, g), &[&x, &y])` for a Newton step on the system `f(x, y) = g(x, y) = 0`.
+//!
+//! Forward mode (`jacobian_forward`) takes one pass per variable and reverse mode (`jacobian_reverse`, with `std`) one pass per output,
+//! so `jacobian` picks whichever needs fewer passes.
+//! When you only need the Jacobian applied to a vector, `jvp` (one forward pass per output) and `vjp` (one reverse pass per output, with `std`)
+//! skip building it altogether.
+
+use crate::forward::{direction, Direction, Dual, Forward};
+
+#[cfg(feature = "std")]
+use crate::{
+    leaf::Leaf,
+    reverse::{Backward, Gradient, Gradients},
+};
+
+/// `M` expressions evaluated together in forward mode, seeded by `W` (implemented for `[E; M]` and tuples).
+pub trait ForwardVector<W, S, const M: usize> {
+    /// Value and derivative with respect to `x` (a reference or a `forward::Direction`) of each output.
+    fn eval_duals(&self, x: W) -> [Dual<S>; M];
+}
+
+/// `M` expressions differentiated together in reverse mode (implemented for `[E; M]` and tuples).
+#[cfg(feature = "std")]
+pub trait BackwardVector<S, const M: usize> {
+    /// Gradient of each output with respect to every variable.
+    fn backward_each(&self) -> [Gradients<S>; M];
+    /// Sum of each output's gradient times its component of `cotangent`, with respect to every variable.
+    fn backward_weighted(&self, cotangent: &[S; M]) -> Gradients<S>;
+}
+
+impl<W: Copy, S, E: Forward<W, Dual = Dual<S>>, const M: usize> ForwardVector<W, S, M> for [E; M] {
+    #[inline(always)]
+    fn eval_duals(&self, x: W) -> [Dual<S>; M] {
+        core::array::from_fn(|i| self[i].eval_dual(x))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S, E: Backward<Value = S>, const M: usize> BackwardVector<S, M> for [E; M]
+where
+    S: Leaf<Unit = &'static S> + Copy + 'static,
+{
+    #[inline(always)]
+    fn backward_each(&self) -> [Gradients<S>; M] {
+        core::array::from_fn(|i| self[i].backward())
+    }
+    #[inline(always)]
+    fn backward_weighted(&self, cotangent: &[S; M]) -> Gradients<S> {
+        let mut grads = Gradients::default();
+        for (e, &c) in self.iter().zip(cotangent) {
+            e.backward_from(c, &mut grads);
+        }
+        grads
+    }
+}
+
+macro_rules! implement_vector {
+    ($m:literal: $($E:ident: $i:tt),+) => {
+        impl<W: Copy, S, $($E: Forward<W, Dual = Dual<S>>),+> ForwardVector<W, S, $m> for ($($E,)+) {
+            #[inline(always)]
+            fn eval_duals(&self, x: W) -> [Dual<S>; $m] {
+                [$(self.$i.eval_dual(x)),+]
+            }
+        }
+        #[cfg(feature = "std")]
+        impl<S, $($E: Backward<Value = S>),+> BackwardVector<S, $m> for ($($E,)+)
+        where
+            S: Leaf<Unit = &'static S> + Copy + 'static,
+        {
+            #[inline(always)]
+            fn backward_each(&self) -> [Gradients<S>; $m] {
+                [$(self.$i.backward()),+]
+            }
+            #[inline(always)]
+            fn backward_weighted(&self, cotangent: &[S; $m]) -> Gradients<S> {
+                let mut grads = Gradients::default();
+                $(self.$i.backward_from(cotangent[$i], &mut grads);)+
+                grads
+            }
+        }
+    };
+}
+
+implement_vector!(1: A: 0);
+implement_vector!(2: A: 0, B: 1);
+implement_vector!(3: A: 0, B: 1, C: 2);
+implement_vector!(4: A: 0, B: 1, C: 2, D: 3);
+implement_vector!(5: A: 0, B: 1, C: 2, D: 3, E: 4);
+implement_vector!(6: A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+implement_vector!(7: A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+implement_vector!(8: A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Jacobian (`J[i][j] = d f_i / d x_j`) in forward mode: one pass over every output per variable.
+#[inline(always)]
+pub fn jacobian_forward<
+    S: Copy,
+    F: for<'x> ForwardVector<&'x S, S, M>,
+    const M: usize,
+    const N: usize,
+>(
+    f: &F,
+    vars: &[&S; N],
+) -> [[S; N]; M] {
+    let columns: [[Dual<S>; M]; N] = core::array::from_fn(|j| f.eval_duals(vars[j]));
+    core::array::from_fn(|i| core::array::from_fn(|j| columns[j][i].deriv))
+}
+
+/// Jacobian (`J[i][j] = d f_i / d x_j`) in reverse mode: one pass over each output for every variable at once.
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn jacobian_reverse<S, F: BackwardVector<S, M>, const M: usize, const N: usize>(
+    f: &F,
+    vars: &[&S; N],
+) -> [[S; N]; M]
+where
+    S: Leaf<Unit = &'static S> + Copy + 'static,
+{
+    let rows = f.backward_each();
+    core::array::from_fn(|i| core::array::from_fn(|j| rows[i].wrt(vars[j])))
+}
+
+/// Jacobian (`J[i][j] = d f_i / d x_j`), in forward mode if there are no more variables than outputs and in reverse mode otherwise.
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn jacobian<
+    S,
+    F: for<'x> ForwardVector<&'x S, S, M> + BackwardVector<S, M>,
+    const M: usize,
+    const N: usize,
+>(
+    f: &F,
+    vars: &[&S; N],
+) -> [[S; N]; M]
+where
+    S: Leaf<Unit = &'static S> + Copy + 'static,
+{
+    if N <= M {
+        jacobian_forward(f, vars)
+    } else {
+        jacobian_reverse(f, vars)
+    }
+}
+
+/// Jacobian (`J[i][j] = d f_i / d x_j`) in forward mode, since reverse mode needs `std`.
+#[cfg(not(feature = "std"))]
+#[inline(always)]
+pub fn jacobian<S: Copy, F: for<'x> ForwardVector<&'x S, S, M>, const M: usize, const N: usize>(
+    f: &F,
+    vars: &[&S; N],
+) -> [[S; N]; M] {
+    jacobian_forward(f, vars)
+}
+
+/// Jacobian-vector product (`(J v)[i] = sum_j d f_i / d x_j v[j]`), i.e. the derivative of every output along `tangent`,
+/// in one forward pass per output.
+#[inline(always)]
+pub fn jvp<
+    S,
+    F: for<'x> ForwardVector<Direction<'x, S, N>, S, M>,
+    const M: usize,
+    const N: usize,
+>(
+    f: &F,
+    vars: &[&S; N],
+    tangent: &[S; N],
+) -> [S; M] {
+    f.eval_duals(direction(vars, tangent))
+        .map(|dual| dual.deriv)
+}
+
+/// Vector-Jacobian product (`(c^T J)[j] = sum_i c[i] d f_i / d x_j`), i.e. the gradient of `c . f`,
+/// in one reverse pass per output.
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn vjp<S, F: BackwardVector<S, M>, const M: usize, const N: usize>(
+    f: &F,
+    vars: &[&S; N],
+    cotangent: &[S; M],
+) -> [S; N]
+where
+    S: Leaf<Unit = &'static S> + Copy + 'static,
+{
+    let grads = f.backward_weighted(cotangent);
+    core::array::from_fn(|j| grads.wrt(vars[j]))
+}
@@ -0,0 +1,223 @@
+//! LaTeX output (e.g. `expr.to_latex()` or `expr.named(&[(&x, "x")]).to_latex()`), so derivations computed by `grad` can be pasted into papers.
+//!
+//! Division prints as `\frac{}{}`, square roots as `\sqrt{}`, powers as superscripts, and functions as `\sin`, `\ln`, etc.
+//! Names are inserted verbatim, so they can be LaTeX themselves (e.g. `"\\theta"`).
+
+use crate::{
+    eval::Eval,
+    expr::Expr,
+    print::{
+        identity, is_empty, is_negative, Binary, Named, Names, Node, Shape, Unary, ATOM, PREFIX,
+    },
+};
+use core::fmt::{self, Display, Formatter, Write};
+
+/// Expression displayed as LaTeX (see `Expr::latex`).
+pub struct Latex<'a, T: ?Sized, N: ?Sized> {
+    expr: &'a T,
+    names: &'a N,
+}
+
+impl<T: Eval + Node> Expr<T> {
+    /// Display as LaTeX, with variables printed as their values.
+    #[inline(always)]
+    pub fn latex(&self) -> Latex<'_, Self, ()> {
+        Latex {
+            expr: self,
+            names: &(),
+        }
+    }
+    /// LaTeX source, with variables printed as their values.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    pub fn to_latex(&self) -> String {
+        self.latex().to_string()
+    }
+}
+
+impl<'a, T: Node + ?Sized, N: Names + ?Sized> Named<'a, T, N> {
+    /// Display as LaTeX, with variables named.
+    #[inline(always)]
+    pub fn latex(&self) -> Latex<'a, T, N> {
+        Latex {
+            expr: self.expr,
+            names: self.names,
+        }
+    }
+    /// LaTeX source, with variables named.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    pub fn to_latex(&self) -> String {
+        self.latex().to_string()
+    }
+}
+
+impl<T: Node + ?Sized, N: Names + ?Sized> Display for Latex<'_, T, N> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        latex(self.expr, self.names, 0, f)
+    }
+}
+
+impl Unary {
+    /// LaTeX command for a function (e.g. `\sin`), or `None` for operators and `exp`/`sqrt` (which have their own notation).
+    #[inline]
+    const fn command(self) -> Option<&'static str> {
+        match self {
+            Unary::Neg | Unary::Not | Unary::Exp | Unary::Sqrt => None,
+            Unary::Ln => Some("\\ln"),
+            Unary::Sin => Some("\\sin"),
+            Unary::Cos => Some("\\cos"),
+            Unary::Tan => Some("\\tan"),
+            Unary::Asin => Some("\\arcsin"),
+            Unary::Acos => Some("\\arccos"),
+            Unary::Atan => Some("\\arctan"),
+            Unary::Sinh => Some("\\sinh"),
+            Unary::Cosh => Some("\\cosh"),
+            Unary::Tanh => Some("\\tanh"),
+        }
+    }
+}
+
+impl Binary {
+    /// LaTeX infix operator and binding power (as in `Binary::infix`), or `None` for fractions and functions.
+    #[inline]
+    const fn latex(self) -> Option<(&'static str, u8)> {
+        match self {
+            Binary::BitOr => Some((" \\lor ", 1)),
+            Binary::BitXor => Some((" \\oplus ", 2)),
+            Binary::BitAnd => Some((" \\land ", 3)),
+            Binary::Shl => Some((" \\ll ", 4)),
+            Binary::Shr => Some((" \\gg ", 4)),
+            Binary::Add => Some((" + ", 5)),
+            Binary::Sub => Some((" - ", 5)),
+            Binary::Mul => Some((" \\cdot ", 6)),
+            Binary::Rem => Some((" \\bmod ", 6)),
+            Binary::Div | Binary::Powf | Binary::Atan2 => None,
+        }
+    }
+}
+
+/// Binding power of a node as printed in LaTeX.
+fn binding<N: Names + ?Sized>(node: &(impl Node + ?Sized), names: &N) -> u8 {
+    match node.shape() {
+        Shape::Var { ptr, .. } if names.name(ptr).is_some() => ATOM,
+        Shape::Var { value, .. } | Shape::Const(value) if is_negative(value) => PREFIX,
+        Shape::Var { .. } | Shape::Const(_) | Shape::Powi(..) => ATOM,
+        Shape::Unary(Unary::Neg | Unary::Not, _) => PREFIX,
+        Shape::Unary(..) => ATOM,
+        Shape::Binary(op, ..) => op.latex().map_or(ATOM, |(_, power)| power),
+        Shape::Nary(_, nary) if is_empty(nary) => ATOM,
+        Shape::Nary(op, _) => op.latex().map_or(ATOM, |(_, power)| power),
+    }
+}
+
+/// Whether a node can be raised to a power without parentheses (e.g. `x^{2}` but not `\sin\left(x\right)^{2}`).
+fn is_base<N: Names + ?Sized>(node: &(impl Node + ?Sized), names: &N) -> bool {
+    match node.shape() {
+        Shape::Var { ptr, .. } if names.name(ptr).is_some() => true,
+        Shape::Var { value, .. } | Shape::Const(value) => !is_negative(value),
+        Shape::Unary(Unary::Sqrt, _) => true,
+        _ => false,
+    }
+}
+
+/// Print `x^{n}`, parenthesizing `x` unless it's a plain variable, constant, or root.
+fn power<N: Names + ?Sized>(
+    x: &(impl Node + ?Sized),
+    n: &dyn Display,
+    names: &N,
+    f: &mut Formatter<'_>,
+) -> fmt::Result {
+    if is_base(x, names) {
+        latex(x, names, 0, f)?;
+    } else {
+        f.write_str("\\left(")?;
+        latex(x, names, 0, f)?;
+        f.write_str("\\right)")?;
+    }
+    write!(f, "^{{{n}}}")
+}
+
+/// Print `node`, parenthesized if it binds less tightly than `min`.
+fn latex<N: Names + ?Sized>(
+    node: &(impl Node + ?Sized),
+    names: &N,
+    min: u8,
+    f: &mut Formatter<'_>,
+) -> fmt::Result {
+    if binding(node, names) < min {
+        f.write_str("\\left(")?;
+        latex(node, names, 0, f)?;
+        return f.write_str("\\right)");
+    }
+    match node.shape() {
+        Shape::Var { ptr, value, .. } => match names.name(ptr) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{value}"),
+        },
+        Shape::Const(value) => write!(f, "{value}"),
+        Shape::Unary(Unary::Neg, x) => {
+            f.write_char('-')?;
+            latex(x, names, ATOM, f) // `-(-x)` rather than `--x`
+        }
+        Shape::Unary(Unary::Not, x) => {
+            f.write_str("\\lnot ")?;
+            latex(x, names, PREFIX, f)
+        }
+        Shape::Unary(Unary::Exp, x) => {
+            f.write_str("e^{")?;
+            latex(x, names, 0, f)?;
+            f.write_char('}')
+        }
+        Shape::Unary(Unary::Sqrt, x) => {
+            f.write_str("\\sqrt{")?;
+            latex(x, names, 0, f)?;
+            f.write_char('}')
+        }
+        Shape::Unary(op, x) => {
+            write!(f, "{}\\left(", op.command().unwrap_or_default())?;
+            latex(x, names, 0, f)?;
+            f.write_str("\\right)")
+        }
+        Shape::Binary(Binary::Div, a, b) => {
+            f.write_str("\\frac{")?;
+            latex(a, names, 0, f)?;
+            f.write_str("}{")?;
+            latex(b, names, 0, f)?;
+            f.write_char('}')
+        }
+        Shape::Binary(Binary::Powf, a, b) => power(a, &Latex { expr: b, names }, names, f),
+        Shape::Binary(Binary::Atan2, a, b) => {
+            f.write_str("\\operatorname{atan2}\\left(")?;
+            latex(a, names, 0, f)?;
+            f.write_str(", ")?;
+            latex(b, names, 0, f)?;
+            f.write_str("\\right)")
+        }
+        Shape::Binary(op, a, b) => {
+            let (symbol, power) = op.latex().unwrap_or_default();
+            // Left-associative: only the right operand needs parentheses at equal binding power
+            latex(a, names, power, f)?;
+            f.write_str(symbol)?;
+            latex(b, names, power + 1, f)
+        }
+        Shape::Powi(x, n) => power(x, &n, names, f),
+        Shape::Nary(op, nary) => {
+            let (symbol, power) = op.latex().unwrap_or_default();
+            let mut empty = true;
+            nary.try_for_each(&mut |x| {
+                if !empty {
+                    f.write_str(symbol)?;
+                }
+                latex(x, names, if empty { power } else { power + 1 }, f)?;
+                empty = false;
+                Ok(())
+            })?;
+            if empty {
+                write!(f, "{}", identity(op))?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -1,6 +1,11 @@
-//! Trait to provide analogies to 0 (`ZERO`) and 1 (`UNIT`).
+//! Leaves of expression trees (variables and constants) and a trait to provide analogies to 0 (`ZERO`) and 1 (`UNIT`).
 
-use crate::{eval::Eval, grad, grad::Grad};
+use crate::{
+    eval::Eval,
+    grad::{Id, Wrt},
+    ops::collapse::Dynamic,
+};
+use core::marker::PhantomData;
 
 /// Analogies to 0 (`ZERO`) and 1 (`UNIT`).
 #[const_trait]
@@ -15,30 +20,97 @@ pub trait Leaf {
 
 crate::implement_eval!(T: Leaf => &T >-> Self: |self| self);
 
-impl<T: ~const Leaf<Unit: ~const Eval>> const grad::Typed for &T {
-    type Differentiated = T::Unit;
+crate::implement_grad!(
+    'a, 'b, T, U => &'a T, wrt &'b U >-> Delta<T> where { T: ~const Leaf<Unit: ~const Eval> }:
+    |self, x| where own { Delta(x.is_ptr(self), PhantomData) } else { Delta(x.is_ptr(*self), PhantomData) }
+);
+// Untagged, so never the variable an `Id` refers to
+crate::implement_grad!(
+    'a, T; const WRT: usize => &'a T, wrt Id<WRT> >-> Zero<T> where { T: ~const Leaf<Unit: ~const Eval> }:
+    |self, _x| where own { Zero(PhantomData) } else { Zero(PhantomData) }
+);
+impl<T: Leaf> Dynamic for &T {}
+
+/// Variable tagged with an ID known at compile time, so we can differentiate with respect to it in a `const`.
+#[derive(Clone, Copy, Debug)]
+pub struct Var<const ID: usize, T>(pub(crate) T);
+
+crate::implement_eval!('a, T; const ID: usize => Var<ID, &'a T> >-> &'a T where { T: ~const Leaf }: |self| self.0);
+crate::implement_grad!(
+    'a, 'b, T, U; const ID: usize => Var<ID, &'a T>, wrt &'b U >-> Delta<T> where { T: ~const Leaf<Unit: ~const Eval> }:
+    |self, x| where own { Delta(x.is_ptr(self.0), PhantomData) } else { Delta(x.is_ptr(self.0), PhantomData) }
+);
+// Both IDs are known statically, so so is the gradient: with `id-folding`, even its type (see `Pick`)
+#[cfg(feature = "id-folding")]
+crate::implement_grad!(
+    'a, T; const ID: usize; const WRT: usize => Var<ID, &'a T>, wrt Id<WRT> >-> <SameId<{ ID == WRT }> as Pick<T>>::Out
+    where { T: ~const Leaf<Unit: ~const Eval>, SameId<{ ID == WRT }>: ~const Pick<T> }:
+    |self, _x| where own { <SameId<{ ID == WRT }> as Pick<T>>::OUT } else { <SameId<{ ID == WRT }> as Pick<T>>::OUT }
+);
+// Otherwise a `Delta`, which still evaluates at compile time
+#[cfg(not(feature = "id-folding"))]
+crate::implement_grad!(
+    'a, T; const ID: usize; const WRT: usize => Var<ID, &'a T>, wrt Id<WRT> >-> Delta<T> where { T: ~const Leaf<Unit: ~const Eval> }:
+    |self, _x| where own { Delta(ID == WRT, PhantomData) } else { Delta(ID == WRT, PhantomData) }
+);
+impl<T: Leaf, const ID: usize> Dynamic for Var<ID, &T> {}
+
+/// Whether a variable's ID is the one we're differentiating with respect to, as a type (see `Pick`).
+#[cfg(feature = "id-folding")]
+pub struct SameId<const SAME: bool>;
+
+/// Gradient of a variable tagged with an ID with respect to an `Id`: statically `One` if they match, `Zero` if not.
+#[cfg(feature = "id-folding")]
+#[const_trait]
+pub trait Pick<T: ~const Leaf> {
+    /// `One` or `Zero`.
+    type Out: ~const Eval;
+    /// The only value of `Out`.
+    const OUT: Self::Out;
 }
-impl<T: ~const Leaf<Unit: ~const Eval>> const grad::Own for &T {
-    #[inline(always)]
-    fn grad<U>(self, x: &U) -> Self::Differentiated {
-        match (x as *const U as *const T).guaranteed_eq(self as *const T) {
-            None => panic!("Couldn't tell whether two values were the same (this often happens at compile time and seems to be an issue with Rust itself)"),
-            Some(false) => T::ZERO,
-            Some(true) => T::UNIT,
-        }
-    }
+
+#[cfg(feature = "id-folding")]
+impl<T: ~const Leaf> const Pick<T> for SameId<true> {
+    type Out = One<T>;
+    const OUT: One<T> = One(PhantomData);
 }
-impl<T: ~const Leaf<Unit: ~const Eval>> const grad::Ref for &T {
-    #[inline(always)]
-    fn grad<U>(&self, x: &U) -> Self::Differentiated {
-        match (x as *const U as *const T).guaranteed_eq(*self as *const T) {
-            None => panic!("Couldn't tell whether two values were the same (this often happens at compile time and seems to be an issue with Rust itself)"),
-            Some(false) => T::ZERO,
-            Some(true) => T::UNIT,
-        }
-    }
+
+#[cfg(feature = "id-folding")]
+impl<T: ~const Leaf> const Pick<T> for SameId<false> {
+    type Out = Zero<T>;
+    const OUT: Zero<T> = Zero(PhantomData);
 }
-impl<T: ~const Leaf<Unit: ~const Eval>> const Grad for &T {}
+
+/// Constant baked into an expression (e.g. the `2` in `2 * x`): evaluates to itself and never varies.
+#[derive(Clone, Copy, Debug)]
+pub struct Lit<T: ~const Leaf>(pub(crate) T);
+
+crate::implement_eval!(T => Lit<T> >-> T where { T: ~const Leaf + Copy }: |self| self.0);
+crate::implement_grad!(T => Lit<T> >-> Zero<T> where { T: ~const Leaf<Unit: ~const Eval> + Copy }: |self, _x| Zero(PhantomData));
+impl<T: Leaf> Dynamic for Lit<T> {}
+
+/// Kronecker delta: `Leaf::UNIT` if two variables are the same and `Leaf::ZERO` otherwise.
+/// Known only at runtime (e.g. `var(&x).grad(&y)`), but constant all the same, so it differentiates to `Zero`.
+#[derive(Clone, Copy, Debug)]
+pub struct Delta<T: ~const Leaf>(pub(crate) bool, pub(crate) PhantomData<T>);
+
+crate::implement_eval!(T => Delta<T> >-> T::Unit where { T: ~const Leaf }: |self| if self.0 { T::UNIT } else { T::ZERO });
+crate::implement_grad!(T => Delta<T> >-> Zero<T> where { T: ~const Leaf<Unit: ~const Eval> }: |self, _x| Zero(PhantomData));
+impl<T: Leaf> Dynamic for Delta<T> {}
+
+/// Statically known `Leaf::ZERO`: anything multiplied by it vanishes at the type level (see `ops::collapse`).
+#[derive(Clone, Copy, Debug)]
+pub struct Zero<T: ~const Leaf>(pub(crate) PhantomData<T>);
+
+crate::implement_eval!(T => Zero<T> >-> T::Unit where { T: ~const Leaf }: |self| T::ZERO);
+crate::implement_grad!(T => Zero<T> >-> Zero<T> where { T: ~const Leaf<Unit: ~const Eval> }: |self, _x| Zero(PhantomData));
+
+/// Statically known `Leaf::UNIT`: anything multiplied by it collapses into itself at the type level (see `ops::collapse`).
+#[derive(Clone, Copy, Debug)]
+pub struct One<T: ~const Leaf>(pub(crate) PhantomData<T>);
+
+crate::implement_eval!(T => One<T> >-> T::Unit where { T: ~const Leaf }: |self| T::UNIT);
+crate::implement_grad!(T => One<T> >-> Zero<T> where { T: ~const Leaf<Unit: ~const Eval> }: |self, _x| Zero(PhantomData));
 
 /// Automagically implement `Leaf`.
 macro_rules! implement_leaf {
@@ -11,7 +11,7 @@
 //! let dvdx = (-var(&x)).grad(&x); // Automatic differentiation!
 //! // ...into another differentiable expression!
 //! assert_eq!(-1, (&dvdx).eval()); // dvdx NOT moved: reusable
-//! assert_eq!(0, dvdx.grad(&x).grad(&x).grad(&x).grad(&x).eval()); // Go ham
+//! assert_eq!(&0, dvdx.grad(&x).grad(&x).grad(&x).grad(&x).eval()); // Go ham (statically `Zero`)
 //! ```
 //!
 //! Want to implement differentiable expressions for your own types and functions?
@@ -30,16 +30,23 @@
 //! At compile time:
 //! ```rust
 //! #![feature(const_trait_impl)]
-//! use dxpr::{expr::Expr, ops::Neg, prelude::*};
+//! # #![cfg_attr(feature = "id-folding", feature(generic_const_exprs), allow(incomplete_features))]
+//! use dxpr::{expr::Expr, leaf::Var, ops::Neg, prelude::*};
 //! const X: i32 = 4;
 //! const A: Expr<&i32> = var(&X);
 //! const EXPRESSION: Expr<Neg<Neg<Neg<&i32>>>> = ---A;
 //! const VALUE: i32 = EXPRESSION.eval();
 //! assert_eq!(-4, VALUE);
-//! // Rust currently can't compare pointers to constants at compile time, so no compile-time autodiff at the moment
-//! // Plans in the works to fix this with the ability to manually specify a "variable ID."
-//! // Either way, your `build.rs` could easily evaluate an arbitrary expression and write it, unevaluated, to a file.
+//! // Rust currently can't compare pointers to constants at compile time,
+//! // so give each variable an ID instead to differentiate at compile time:
+//! const B: Expr<Var<0, &i32>> = var_id::<0, _>(&X);
+//! const DVDB: i32 = (-(B * B)).grad_id::<0>().eval();
+//! assert_eq!(-8, DVDB);
 //! ```
+//!
+//! With the `id-folding` feature, gradients with respect to variable IDs are statically `One` or `Zero`
+//! (e.g. `var_id::<0, _>(&X).grad_id::<0>()` is an `Expr<One<i32>>`), so they fold away at the type level.
+//! This relies on the incomplete `generic_const_exprs` feature, which every crate using `id-folding` must enable too.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![deny(warnings, missing_docs)]
@@ -50,18 +57,48 @@
     const_raw_ptr_comparison,
     const_trait_impl
 )]
+// To compare variable IDs at the type level (see `leaf::Pick`); crates using `id-folding` need it too
+#![cfg_attr(
+    feature = "id-folding",
+    feature(generic_const_exprs),
+    allow(incomplete_features)
+)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
+#[cfg(feature = "std")]
+pub mod codegen;
+pub mod dot;
+#[cfg(feature = "alloc")]
+pub mod dynamic;
 pub mod eval;
 pub mod expr;
+pub mod float;
+pub mod forward;
 pub mod grad;
+pub mod jacobian;
+pub mod latex;
 pub mod leaf;
 pub mod ops;
+#[cfg(feature = "std")]
+pub mod parse;
+pub mod print;
+#[cfg(feature = "std")]
+pub mod reverse;
+pub mod simplify;
 
 /// Convenient traits.
 pub mod prelude {
+    #[cfg(feature = "alloc")]
+    pub use crate::dynamic::IntoDyn as _;
     pub use crate::eval::{Own as _, Ref as _};
-    pub use crate::expr::var;
-    pub use crate::grad::{Own as _, Ref as _};
+    pub use crate::expr::{lit, product, sum, var, var_id};
+    pub use crate::forward::Forward as _;
+    pub use crate::grad::{GradAll as _, Own as _, Ref as _};
+    #[cfg(feature = "std")]
+    pub use crate::reverse::{Backward as _, Gradient as _};
+    pub use crate::simplify::Simplify as _;
 }
 
 #[cfg(test)]
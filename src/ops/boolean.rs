@@ -0,0 +1,75 @@
+//! Boolean difference as the gradient of logic: `df/dx = f(x = false) ^ f(x = true)`,
+//! i.e. whether flipping `x` flips `f`, so the gradient of a `bool` expression says which inputs it actually depends on.
+//! Over `bool`, `^` is addition and `&` is multiplication, and these rules are exact wherever they're evaluated.
+//! They only hold over `bool`, though (e.g. `!x` is `-1 - x` over `i32`), so only `bool` expressions get them.
+
+use super::*;
+use crate::grad::Typed;
+
+type Diff<T, W> = <T as Typed<W>>::Differentiated;
+type And<L, R> = <L as collapse::BitAnd<R>>::Output;
+type Xor<L, R> = <L as collapse::BitXor<R>>::Output;
+
+// Flipping the input always flips the output, so d(!u) = du
+crate::implement_grad!(
+    T => Not<T> >-> Diff<T, W>
+    where { T: ~const Eval<Evaluated: ~const core::ops::Not<Output = bool>> + ~const Grad<W> }:
+    |self, x| where own { self.0.grad(x) } else { (&self.0).grad(x) }
+);
+
+// Linear: d(u ^ v) = du ^ dv
+crate::implement_grad!(
+    L, R => BitXor<L, R> >-> Xor<Diff<L, W>, Diff<R, W>>
+    where {
+        L: ~const Eval<Evaluated: ~const core::ops::BitXor<R::Evaluated, Output = bool>>
+            + ~const Grad<W, Differentiated: ~const collapse::BitXor<Diff<R, W>>>,
+        R: ~const Grad<W>,
+    }:
+    |self, x| where own {
+        collapse::BitXor::bitxor(self.0.grad(x), self.1.grad(x))
+    } else {
+        collapse::BitXor::bitxor((&self.0).grad(x), (&self.1).grad(x))
+    }
+);
+
+// Product rule plus a second-order term: d(uv) = u dv ^ du v ^ du dv
+crate::implement_grad!(
+    L, R => BitAnd<L, R> >-> Xor<Xor<And<L, Diff<R, W>>, And<Diff<L, W>, R>>, And<Diff<L, W>, Diff<R, W>>>
+    where {
+        L: ~const Eval<Evaluated: ~const core::ops::BitAnd<R::Evaluated, Output = bool>>
+            + ~const Grad<W, Differentiated: ~const collapse::BitAnd<R> + ~const collapse::BitAnd<Diff<R, W>>>
+            + ~const collapse::BitAnd<Diff<R, W>>
+            + Copy,
+        R: ~const Grad<W> + Copy,
+        And<L, Diff<R, W>>: ~const collapse::BitXor<And<Diff<L, W>, R>>,
+        Xor<And<L, Diff<R, W>>, And<Diff<L, W>, R>>: ~const collapse::BitXor<And<Diff<L, W>, Diff<R, W>>>,
+    }:
+    |self, x| collapse::BitXor::bitxor(
+        collapse::BitXor::bitxor(
+            collapse::BitAnd::bitand(self.0, self.1.grad(x)),
+            collapse::BitAnd::bitand(self.0.grad(x), self.1),
+        ),
+        collapse::BitAnd::bitand(self.0.grad(x), self.1.grad(x)),
+    )
+);
+
+// De Morgan on the product rule: d(u | v) = !u dv ^ du !v ^ du dv
+crate::implement_grad!(
+    L, R => BitOr<L, R> >-> Xor<Xor<And<Not<L>, Diff<R, W>>, And<Diff<L, W>, Not<R>>>, And<Diff<L, W>, Diff<R, W>>>
+    where {
+        L: ~const Eval<Evaluated: ~const core::ops::BitOr<R::Evaluated, Output = bool> + ~const core::ops::Not>
+            + ~const Grad<W, Differentiated: ~const collapse::BitAnd<Not<R>> + ~const collapse::BitAnd<Diff<R, W>>>
+            + Copy,
+        R: ~const Eval<Evaluated: ~const core::ops::Not> + ~const Grad<W> + Copy,
+        Not<L>: ~const collapse::BitAnd<Diff<R, W>>,
+        And<Not<L>, Diff<R, W>>: ~const collapse::BitXor<And<Diff<L, W>, Not<R>>>,
+        Xor<And<Not<L>, Diff<R, W>>, And<Diff<L, W>, Not<R>>>: ~const collapse::BitXor<And<Diff<L, W>, Diff<R, W>>>,
+    }:
+    |self, x| collapse::BitXor::bitxor(
+        collapse::BitXor::bitxor(
+            collapse::BitAnd::bitand(Not(self.0), self.1.grad(x)),
+            collapse::BitAnd::bitand(self.0.grad(x), Not(self.1)),
+        ),
+        collapse::BitAnd::bitand(self.0.grad(x), self.1.grad(x)),
+    )
+);
@@ -0,0 +1,347 @@
+//! Constructors that fold statically known `Zero`s and `One`s at the type level.
+//! Gradients are built out of these, so constant subtrees vanish instead of piling up `x + 0` and `1 * x`,
+//! which keeps both the size of gradient types and the cost of evaluating them down.
+//!
+//! Values known only at runtime (e.g. `var(&x).grad(&y)`, which depends on whether `x` is `y`) are `Dynamic` and never fold.
+
+use crate::{
+    eval::Eval,
+    leaf::{Leaf, One, Zero},
+    ops,
+};
+
+/// Not statically `Zero` or `One`: value known only once evaluated.
+pub trait Dynamic {}
+
+/// Not statically `Zero`.
+pub trait Nonzero {}
+impl<T: Leaf> Nonzero for One<T> {}
+impl<T: Dynamic> Nonzero for T {}
+
+/// Addition, dropping `Zero` terms.
+#[const_trait]
+pub trait Add<R> {
+    /// Folded type.
+    type Output: ~const Eval;
+    /// Fold `self + r`.
+    fn add(self, r: R) -> Self::Output;
+}
+
+impl<T: ~const Leaf, R: ~const Eval> const Add<R> for Zero<T> {
+    type Output = R;
+    #[inline(always)]
+    fn add(self, r: R) -> R {
+        r
+    }
+}
+
+impl<L: Nonzero + ~const Eval, U: ~const Leaf> const Add<Zero<U>> for L {
+    type Output = L;
+    #[inline(always)]
+    fn add(self, _r: Zero<U>) -> L {
+        self
+    }
+}
+
+impl<L: Nonzero + ~const Eval<Evaluated: ~const core::ops::Add<R::Evaluated>>, R: Nonzero + ~const Eval>
+    const Add<R> for L
+{
+    type Output = ops::Add<L, R>;
+    #[inline(always)]
+    fn add(self, r: R) -> Self::Output {
+        ops::Add(self, r)
+    }
+}
+
+/// Negation, keeping `Zero` as is.
+#[const_trait]
+pub trait Neg {
+    /// Folded type.
+    type Output: ~const Eval;
+    /// Fold `-self`.
+    fn neg(self) -> Self::Output;
+}
+
+impl<T: ~const Leaf> const Neg for Zero<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn neg(self) -> Self {
+        self
+    }
+}
+
+impl<T: Nonzero + ~const Eval<Evaluated: ~const core::ops::Neg>> const Neg for T {
+    type Output = ops::Neg<T>;
+    #[inline(always)]
+    fn neg(self) -> Self::Output {
+        ops::Neg(self)
+    }
+}
+
+/// Subtraction, dropping `Zero` terms.
+#[const_trait]
+pub trait Sub<R> {
+    /// Folded type.
+    type Output: ~const Eval;
+    /// Fold `self - r`.
+    fn sub(self, r: R) -> Self::Output;
+}
+
+impl<T: ~const Leaf, R: ~const Neg> const Sub<R> for Zero<T> {
+    type Output = R::Output;
+    #[inline(always)]
+    fn sub(self, r: R) -> R::Output {
+        r.neg()
+    }
+}
+
+impl<L: Nonzero + ~const Eval, U: ~const Leaf> const Sub<Zero<U>> for L {
+    type Output = L;
+    #[inline(always)]
+    fn sub(self, _r: Zero<U>) -> L {
+        self
+    }
+}
+
+impl<L: Nonzero + ~const Eval<Evaluated: ~const core::ops::Sub<R::Evaluated>>, R: Nonzero + ~const Eval>
+    const Sub<R> for L
+{
+    type Output = ops::Sub<L, R>;
+    #[inline(always)]
+    fn sub(self, r: R) -> Self::Output {
+        ops::Sub(self, r)
+    }
+}
+
+/// Multiplication, vanishing at `Zero` and dropping `One` factors.
+#[const_trait]
+pub trait Mul<R> {
+    /// Folded type.
+    type Output: ~const Eval;
+    /// Fold `self * r`.
+    fn mul(self, r: R) -> Self::Output;
+}
+
+impl<T: ~const Leaf, R: ~const Eval> const Mul<R> for Zero<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, r: R) -> Self {
+        core::mem::forget(r);
+        self
+    }
+}
+
+impl<L: Nonzero + ~const Eval, U: ~const Leaf> const Mul<Zero<U>> for L {
+    type Output = Zero<U>;
+    #[inline(always)]
+    fn mul(self, r: Zero<U>) -> Zero<U> {
+        core::mem::forget(self);
+        r
+    }
+}
+
+impl<T: ~const Leaf, R: Nonzero + ~const Eval> const Mul<R> for One<T> {
+    type Output = R;
+    #[inline(always)]
+    fn mul(self, r: R) -> R {
+        r
+    }
+}
+
+impl<L: Dynamic + ~const Eval, U: ~const Leaf> const Mul<One<U>> for L {
+    type Output = L;
+    #[inline(always)]
+    fn mul(self, _r: One<U>) -> L {
+        self
+    }
+}
+
+impl<L: Dynamic + ~const Eval<Evaluated: ~const core::ops::Mul<R::Evaluated>>, R: Dynamic + ~const Eval>
+    const Mul<R> for L
+{
+    type Output = ops::Mul<L, R>;
+    #[inline(always)]
+    fn mul(self, r: R) -> Self::Output {
+        ops::Mul(self, r)
+    }
+}
+
+/// Division, vanishing at a `Zero` numerator and dropping `One` denominators.
+#[const_trait]
+pub trait Div<R> {
+    /// Folded type.
+    type Output: ~const Eval;
+    /// Fold `self / r`.
+    fn div(self, r: R) -> Self::Output;
+}
+
+impl<T: ~const Leaf, R: Nonzero + ~const Eval> const Div<R> for Zero<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn div(self, r: R) -> Self {
+        core::mem::forget(r);
+        self
+    }
+}
+
+impl<L: Nonzero + ~const Eval, U: ~const Leaf> const Div<One<U>> for L {
+    type Output = L;
+    #[inline(always)]
+    fn div(self, _r: One<U>) -> L {
+        self
+    }
+}
+
+impl<L: Nonzero + ~const Eval<Evaluated: ~const core::ops::Div<R::Evaluated>>, R: Dynamic + ~const Eval>
+    const Div<R> for L
+{
+    type Output = ops::Div<L, R>;
+    #[inline(always)]
+    fn div(self, r: R) -> Self::Output {
+        ops::Div(self, r)
+    }
+}
+
+/// Left shift, vanishing at `Zero` (the shift amount is discrete, so only the left side can fold).
+#[const_trait]
+pub trait Shl<R> {
+    /// Folded type.
+    type Output: ~const Eval;
+    /// Fold `self << r`.
+    fn shl(self, r: R) -> Self::Output;
+}
+
+impl<T: ~const Leaf, R: ~const Eval> const Shl<R> for Zero<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn shl(self, r: R) -> Self {
+        core::mem::forget(r);
+        self
+    }
+}
+
+impl<L: Nonzero + ~const Eval<Evaluated: ~const core::ops::Shl<R::Evaluated>>, R: ~const Eval> const
+    Shl<R> for L
+{
+    type Output = ops::Shl<L, R>;
+    #[inline(always)]
+    fn shl(self, r: R) -> Self::Output {
+        ops::Shl(self, r)
+    }
+}
+
+/// Right shift, vanishing at `Zero` (the shift amount is discrete, so only the left side can fold).
+#[const_trait]
+pub trait Shr<R> {
+    /// Folded type.
+    type Output: ~const Eval;
+    /// Fold `self >> r`.
+    fn shr(self, r: R) -> Self::Output;
+}
+
+impl<T: ~const Leaf, R: ~const Eval> const Shr<R> for Zero<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn shr(self, r: R) -> Self {
+        core::mem::forget(r);
+        self
+    }
+}
+
+impl<L: Nonzero + ~const Eval<Evaluated: ~const core::ops::Shr<R::Evaluated>>, R: ~const Eval> const
+    Shr<R> for L
+{
+    type Output = ops::Shr<L, R>;
+    #[inline(always)]
+    fn shr(self, r: R) -> Self::Output {
+        ops::Shr(self, r)
+    }
+}
+
+/// Bitwise conjunction, vanishing at `Zero` and dropping `One` operands (i.e. multiplication over `bool`).
+#[const_trait]
+pub trait BitAnd<R> {
+    /// Folded type.
+    type Output: ~const Eval;
+    /// Fold `self & r`.
+    fn bitand(self, r: R) -> Self::Output;
+}
+
+impl<T: ~const Leaf, R: ~const Eval> const BitAnd<R> for Zero<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn bitand(self, r: R) -> Self {
+        core::mem::forget(r);
+        self
+    }
+}
+
+impl<L: Nonzero + ~const Eval, U: ~const Leaf> const BitAnd<Zero<U>> for L {
+    type Output = Zero<U>;
+    #[inline(always)]
+    fn bitand(self, r: Zero<U>) -> Zero<U> {
+        core::mem::forget(self);
+        r
+    }
+}
+
+impl<T: ~const Leaf, R: Nonzero + ~const Eval> const BitAnd<R> for One<T> {
+    type Output = R;
+    #[inline(always)]
+    fn bitand(self, r: R) -> R {
+        r
+    }
+}
+
+impl<L: Dynamic + ~const Eval, U: ~const Leaf> const BitAnd<One<U>> for L {
+    type Output = L;
+    #[inline(always)]
+    fn bitand(self, _r: One<U>) -> L {
+        self
+    }
+}
+
+impl<L: Dynamic + ~const Eval<Evaluated: ~const core::ops::BitAnd<R::Evaluated>>, R: Dynamic + ~const Eval>
+    const BitAnd<R> for L
+{
+    type Output = ops::BitAnd<L, R>;
+    #[inline(always)]
+    fn bitand(self, r: R) -> Self::Output {
+        ops::BitAnd(self, r)
+    }
+}
+
+/// Bitwise exclusive-or, dropping `Zero` terms (i.e. addition over `bool`).
+#[const_trait]
+pub trait BitXor<R> {
+    /// Folded type.
+    type Output: ~const Eval;
+    /// Fold `self ^ r`.
+    fn bitxor(self, r: R) -> Self::Output;
+}
+
+impl<T: ~const Leaf, R: ~const Eval> const BitXor<R> for Zero<T> {
+    type Output = R;
+    #[inline(always)]
+    fn bitxor(self, r: R) -> R {
+        r
+    }
+}
+
+impl<L: Nonzero + ~const Eval, U: ~const Leaf> const BitXor<Zero<U>> for L {
+    type Output = L;
+    #[inline(always)]
+    fn bitxor(self, _r: Zero<U>) -> L {
+        self
+    }
+}
+
+impl<L: Nonzero + ~const Eval<Evaluated: ~const core::ops::BitXor<R::Evaluated>>, R: Nonzero + ~const Eval>
+    const BitXor<R> for L
+{
+    type Output = ops::BitXor<L, R>;
+    #[inline(always)]
+    fn bitxor(self, r: R) -> Self::Output {
+        ops::BitXor(self, r)
+    }
+}
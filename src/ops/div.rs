@@ -0,0 +1,32 @@
+use super::*;
+
+// Quotient rule: (u/v)' = (u'v - uv') / v^2
+// Like the product rule, each side appears more than once, so both have to be `Copy`.
+crate::implement_grad!(
+    L, R => Div<L, R> >-> <
+        <
+            <L::Differentiated as collapse::Mul<R>>::Output as collapse::Sub<
+                <L as collapse::Mul<R::Differentiated>>::Output,
+            >
+        >::Output as collapse::Div<<R as collapse::Mul<R>>::Output>
+    >::Output
+    where {
+        L: ~const Eval<Evaluated: ~const core::ops::Div<R::Evaluated>>
+            + ~const Grad<W, Differentiated: ~const collapse::Mul<R>>
+            + ~const collapse::Mul<R::Differentiated>
+            + Copy,
+        R: ~const Eval + ~const Grad<W> + ~const collapse::Mul<R> + Copy,
+        <L::Differentiated as collapse::Mul<R>>::Output:
+            ~const collapse::Sub<<L as collapse::Mul<R::Differentiated>>::Output>,
+        <<L::Differentiated as collapse::Mul<R>>::Output as collapse::Sub<
+            <L as collapse::Mul<R::Differentiated>>::Output,
+        >>::Output: ~const collapse::Div<<R as collapse::Mul<R>>::Output>,
+    }:
+    |self, x| collapse::Div::div(
+        collapse::Sub::sub(
+            collapse::Mul::mul(self.0.grad(x), self.1),
+            collapse::Mul::mul(self.0, self.1.grad(x)),
+        ),
+        collapse::Mul::mul(self.1, self.1),
+    )
+);
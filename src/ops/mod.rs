@@ -8,15 +8,29 @@
 //! But we can always get away with keeping `Expr` wrapping only the outer layer:
 //! every time we add an operation to the tree, keep only its `self.0`.
 
+mod boolean;
+pub mod collapse;
+mod div;
 mod mul;
+mod nary;
+mod rem;
+mod transcendental;
 
-use crate::{eval, eval::Eval, expr::Expr, grad, grad::Grad};
+pub use nary::{Product, ProductRule, Sum};
+pub use transcendental::{
+    Acos, Asin, Atan, Atan2, Cos, Cosh, Exp, Ln, Monomial, Powf, Powi, Sin, Sinh, Sqrt, Tan, Tanh,
+    ZeroMul,
+};
+
+use crate::{eval::Eval, expr::Expr, grad::Grad, leaf::Lit};
+use collapse::Dynamic;
 
 macro_rules! unary_op {
     ($Name:ident, $name:ident, $doc:literal) => {
-        #[derive(Debug)]
+        #[derive(Clone, Copy, Debug)]
         #[doc = $doc]
-        pub struct $Name<T: ~const Eval>(T);
+        pub struct $Name<T: ~const Eval>(pub(crate) T);
+        impl<T: Eval> Dynamic for $Name<T> {}
         impl<T: ~const Eval<Evaluated: ~const core::ops::$Name>> const core::ops::$Name for Expr<T> {
             type Output = Expr<$Name<T>>;
             #[inline(always)]
@@ -37,59 +51,28 @@ macro_rules! unary_op {
 }
 
 macro_rules! unary_grad {
-    ($Name:ident, $Diff:ident) => {
-        // TODO: see end of this block
-        impl<
+    ($Name:ident, $name:ident) => {
+        $crate::implement_grad!(
+            T => $Name<T> >-> <T::Differentiated as collapse::$Name>::Output
+            where {
                 T: ~const Eval<Evaluated: ~const core::ops::$Name>
-                    + ~const Grad<Differentiated: ~const Eval<Evaluated: ~const core::ops::$Diff>>,
-            > const grad::Typed for $Name<T>
-        {
-            type Differentiated = $Diff<T::Differentiated>;
-        }
-        impl<
-                T: ~const Eval<Evaluated: ~const core::ops::$Name>
-                    + ~const Grad<Differentiated: ~const Eval<Evaluated: ~const core::ops::$Diff>>,
-            > const grad::Own for $Name<T>
-        {
-            #[inline(always)]
-            fn grad<U>(self, x: &U) -> Self::Differentiated {
-                $Diff(self.0.grad(x))
-            }
-        }
-        impl<
-                T: ~const Eval<Evaluated: ~const core::ops::$Name>
-                    + ~const Grad<Differentiated: ~const Eval<Evaluated: ~const core::ops::$Diff>>,
-            > const grad::Ref for $Name<T>
-        {
-            #[inline(always)]
-            fn grad<U>(&self, x: &U) -> Self::Differentiated {
-                $Diff((&self.0).grad(x))
+                    + ~const Grad<W, Differentiated: ~const collapse::$Name>,
+            }:
+            |self, x| where own {
+                collapse::$Name::$name(self.0.grad(x))
+            } else {
+                collapse::$Name::$name((&self.0).grad(x))
             }
-        }
-        impl<
-                T: ~const Eval<Evaluated: ~const core::ops::$Name>
-                    + ~const Grad<Differentiated: ~const Eval<Evaluated: ~const core::ops::$Diff>>,
-            > const Grad for $Name<T>
-        {
-        }
-        // TODO: Rust doesn't like `+`, but apart from that, this should work
-        // $crate::implement_grad!(
-        //     T: Eval<Evaluated: ~const core::ops::$Name> + ~const Grad<Differentiated: ~const Eval<Evaluated: ~const core::ops::$Diff>> =>
-        //     $Name<T> >-> $Diff<T::Differentiated>:
-        //     |self, x| where own {
-        //         $Diff(self.0.grad(x))
-        //     } else {
-        //         $Diff((&self.0).grad(x))
-        //     }
-        // );
+        );
     };
 }
 
 macro_rules! binary_op {
     ($Name:ident, $name:ident, $doc:literal) => {
-        #[derive(Debug)]
+        #[derive(Clone, Copy, Debug)]
         #[doc = $doc]
-        pub struct $Name<L: ~const Eval, R: ~const Eval>(L, R);
+        pub struct $Name<L: ~const Eval, R: ~const Eval>(pub(crate) L, pub(crate) R);
+        impl<L: Eval, R: Eval> Dynamic for $Name<L, R> {}
         impl<
                 L: ~const Eval<Evaluated: ~const core::ops::$Name<R::Evaluated>>,
                 R: ~const Eval,
@@ -101,6 +84,7 @@ macro_rules! binary_op {
                 Expr($Name(self.0, arg.0))
             }
         }
+        binary_lit!($Name, $name: bool, u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
         $crate::implement_eval!(
             L: Eval<Evaluated: ~const core::ops::$Name<R::Evaluated>>, R: Eval =>
             $Name<L, R> >-> <L::Evaluated as core::ops::$Name<R::Evaluated>>::Output:
@@ -113,69 +97,68 @@ macro_rules! binary_op {
     };
 }
 
+/// Mix builtin constants into expressions on either side (e.g. `2 * x` or `x + 1.`) by wrapping them in `Lit`.
+macro_rules! binary_lit {
+    ($Name:ident, $name:ident: $($t:ty),+) => {
+        $(
+            impl<L: ~const Eval<Evaluated: ~const core::ops::$Name<$t>>> const core::ops::$Name<$t>
+                for Expr<L>
+            {
+                type Output = Expr<$Name<L, Lit<$t>>>;
+                #[inline(always)]
+                fn $name(self, arg: $t) -> Self::Output {
+                    Expr($Name(self.0, Lit(arg)))
+                }
+            }
+            impl<R: ~const Eval> const core::ops::$Name<Expr<R>> for $t
+            where
+                $t: ~const core::ops::$Name<R::Evaluated>,
+            {
+                type Output = Expr<$Name<Lit<$t>, R>>;
+                #[inline(always)]
+                fn $name(self, arg: Expr<R>) -> Self::Output {
+                    Expr($Name(Lit(self), arg.0))
+                }
+            }
+        )+
+    };
+}
+
 macro_rules! binary_grad {
-    ($Name:ident, $Diff:ident) => {
-        impl<
-                L: ~const Eval<Evaluated: ~const core::ops::$Name<R::Evaluated>>
-                    + ~const Grad<
-                        Differentiated: ~const Eval<
-                            Evaluated: ~const core::ops::$Diff<
-                                <R::Differentiated as eval::Typed>::Evaluated,
-                            >,
-                        >,
-                    >,
-                R: ~const Grad,
-            > const grad::Typed for $Name<L, R>
-        {
-            type Differentiated = $Diff<L::Differentiated, R::Differentiated>;
-        }
-        impl<
+    ($Name:ident, $name:ident) => {
+        $crate::implement_grad!(
+            L, R => $Name<L, R> >-> <L::Differentiated as collapse::$Name<R::Differentiated>>::Output
+            where {
                 L: ~const Eval<Evaluated: ~const core::ops::$Name<R::Evaluated>>
-                    + ~const Grad<
-                        Differentiated: ~const Eval<
-                            Evaluated: ~const core::ops::$Diff<
-                                <R::Differentiated as eval::Typed>::Evaluated,
-                            >,
-                        >,
-                    >,
-                R: ~const Grad,
-            > const grad::Own for $Name<L, R>
-        {
-            #[inline(always)]
-            fn grad<U>(self, x: &U) -> Self::Differentiated {
-                $Diff(self.0.grad(x), self.1.grad(x))
+                    + ~const Grad<W, Differentiated: ~const collapse::$Name<R::Differentiated>>,
+                R: ~const Grad<W>,
+            }:
+            |self, x| where own {
+                collapse::$Name::$name(self.0.grad(x), self.1.grad(x))
+            } else {
+                collapse::$Name::$name((&self.0).grad(x), (&self.1).grad(x))
             }
-        }
-        impl<
+        );
+    };
+}
+
+/// Shifts scale by a power of two, so `(a << b)' = a' << b` (i.e. `a' * 2^b`):
+/// the shift amount is discrete, so it's treated as a constant and its gradient never flows.
+macro_rules! shift_grad {
+    ($Name:ident, $name:ident) => {
+        $crate::implement_grad!(
+            L, R => $Name<L, R> >-> <L::Differentiated as collapse::$Name<R>>::Output
+            where {
                 L: ~const Eval<Evaluated: ~const core::ops::$Name<R::Evaluated>>
-                    + ~const Grad<
-                        Differentiated: ~const Eval<
-                            Evaluated: ~const core::ops::$Diff<
-                                <R::Differentiated as eval::Typed>::Evaluated,
-                            >,
-                        >,
-                    >,
-                R: ~const Grad,
-            > const grad::Ref for $Name<L, R>
-        {
-            #[inline(always)]
-            fn grad<U>(&self, x: &U) -> Self::Differentiated {
-                $Diff((&self.0).grad(x), (&self.1).grad(x))
+                    + ~const Grad<W, Differentiated: ~const collapse::$Name<R>>,
+                R: ~const Eval + Copy,
+            }:
+            |self, x| where own {
+                collapse::$Name::$name(self.0.grad(x), self.1)
+            } else {
+                collapse::$Name::$name((&self.0).grad(x), self.1)
             }
-        }
-        impl<
-                L: ~const Eval<Evaluated: ~const core::ops::$Name<R::Evaluated>>
-                    + ~const Grad<
-                        Differentiated: ~const Eval<
-                            Evaluated: ~const core::ops::$Diff<
-                                <R::Differentiated as eval::Typed>::Evaluated,
-                            >,
-                        >,
-                    >,
-                R: ~const Grad,
-            > const Grad for $Name<L, R>
-        {
-        }
+        );
     };
 }
 
@@ -184,22 +167,23 @@ macro_rules! binary_grad {
 unary_op!(Neg, neg, "Arithmetic negation (e.g. `-4`).");
 unary_op!(Not, not, "Logical negation (e.g. `!true`).");
 
-unary_grad!(Neg, Neg);
-// TODO...
+unary_grad!(Neg, neg);
 
 binary_op!(Add, add, "Arithmetic addition (e.g. `a + b`");
 binary_op!(BitAnd, bitand, "Bitwise conjunction (e.g. `a & b`)");
 binary_op!(BitOr, bitor, "Bitwise inclusive-or (e.g. `a | b`)");
 binary_op!(BitXor, bitxor, "Bitwise exclusive-or (e.g. `a ^ b`)");
-binary_op!(Div, div, "Arithmetic division (e.g. `a / b`)"); // TODO: quotient rule
-binary_op!(Mul, mul, "Arithmetic multiplication (e.g. `a * b`)"); // TODO: product rule
-binary_op!(Rem, rem, "Arithmetic remainder (e.g. `a % b`)"); //TODO: just the left argument
-binary_op!(Shl, shl, "Arithmetic left-shift (e.g. `a << b`)"); //TODO: more complicated
-binary_op!(Shr, shr, "Arithmetic right-shift (e.g. `a << b`)"); //TODO: more complicated
+binary_op!(Div, div, "Arithmetic division (e.g. `a / b`)");
+binary_op!(Mul, mul, "Arithmetic multiplication (e.g. `a * b`)");
+binary_op!(Rem, rem, "Arithmetic remainder (e.g. `a % b`)");
+binary_op!(Shl, shl, "Arithmetic left-shift (e.g. `a << b`)");
+binary_op!(Shr, shr, "Arithmetic right-shift (e.g. `a >> b`)");
 binary_op!(Sub, sub, "Arithmetic subtraction (e.g. `a - b`)"); //TODO: more complicated
 
-binary_grad!(Add, Add);
-binary_grad!(Sub, Sub);
+binary_grad!(Add, add);
+binary_grad!(Sub, sub);
+shift_grad!(Shl, shl);
+shift_grad!(Shr, shr);
 
 // TODO:
 // binary_op!(Index, index, "Subscript indexing (e.g. `a[b]`)");
@@ -0,0 +1,158 @@
+//! Flat sums and products of any number of terms of one type (see `expr::sum` and `expr::product`),
+//! so that adding up a thousand terms makes one node rather than a thousand-deep nest of `Add`s.
+//! Terms can be masked out, which is how each term of a product rule's gradient leaves out the factor it differentiates.
+//! Their length is part of their type, so there's no `Iterator::sum` for `Expr`: that's what `DynExpr` is for.
+
+use super::*;
+use crate::{eval::Typed, leaf::Leaf};
+
+/// Flat sum of the terms not masked out (see `expr::sum`).
+#[derive(Clone, Copy, Debug)]
+pub struct Sum<T: ~const Eval, const N: usize>(pub(crate) [T; N], pub(crate) [bool; N]);
+impl<T: Eval, const N: usize> Dynamic for Sum<T, N> {}
+
+/// Flat product of the factors not masked out (see `expr::product`).
+#[derive(Clone, Copy, Debug)]
+pub struct Product<T: ~const Eval, const N: usize>(pub(crate) [T; N], pub(crate) [bool; N]);
+impl<T: Eval, const N: usize> Dynamic for Product<T, N> {}
+
+/// Generalized product rule over the factors not masked out (the gradient of a `Product`):
+/// each factor's gradient times all the other factors, summed up in one pass, without division.
+#[derive(Clone, Copy, Debug)]
+pub struct ProductRule<T: ~const Eval, D: ~const Eval, const N: usize>(
+    pub(crate) [T; N],
+    pub(crate) [D; N],
+    pub(crate) [bool; N],
+);
+impl<T: Eval, D: Eval, const N: usize> Dynamic for ProductRule<T, D, N> {}
+
+impl<T: Eval, const N: usize> Sum<T, N> {
+    /// Terms not masked out.
+    #[inline]
+    pub(crate) fn terms(&self) -> impl Iterator<Item = &T> {
+        self.0
+            .iter()
+            .zip(self.1)
+            .filter_map(|(t, keep)| keep.then_some(t))
+    }
+}
+
+impl<T: Eval, const N: usize> Product<T, N> {
+    /// Factors not masked out.
+    #[inline]
+    pub(crate) fn factors(&self) -> impl Iterator<Item = &T> {
+        self.0
+            .iter()
+            .zip(self.1)
+            .filter_map(|(t, keep)| keep.then_some(t))
+    }
+}
+
+impl<T: Eval, D: Eval, const N: usize> ProductRule<T, D, N> {
+    /// Factors not masked out, with their gradients.
+    #[inline]
+    pub(crate) fn pairs(&self) -> impl Iterator<Item = (&T, &D)> {
+        self.0
+            .iter()
+            .zip(&self.1)
+            .zip(self.2)
+            .filter_map(|(pair, keep)| keep.then_some(pair))
+    }
+}
+
+/// What a `Sum` of `T`s evaluates to.
+type Total<T> = <<T as Typed>::Evaluated as core::ops::Add>::Output;
+/// What a `Product` of `T`s evaluates to.
+type Multiple<T> = <<T as Typed>::Evaluated as core::ops::Mul>::Output;
+
+// Folds start from `Leaf::ZERO` and `Leaf::UNIT`, so an empty sum is zero, an empty product is one,
+// and terms that evaluate to references (e.g. `var(&x)` to `&f64`) add up to an owned value.
+crate::implement_eval!(
+    runtime T; const N: usize => Sum<T, N> >-> Total<T>
+    where {
+        T: Eval<Evaluated: core::ops::Add>,
+        Total<T>: Leaf<Unit = &'static Total<T>> + core::ops::Add<T::Evaluated, Output = Total<T>> + Copy + 'static,
+    }:
+    |self| self.terms().fold(*<Total<T> as Leaf>::ZERO, |sum, t| sum + crate::eval::Ref::eval(t))
+);
+crate::implement_eval!(
+    runtime T; const N: usize => Product<T, N> >-> Multiple<T>
+    where {
+        T: Eval<Evaluated: core::ops::Mul>,
+        Multiple<T>: Leaf<Unit = &'static Multiple<T>> + core::ops::Mul<T::Evaluated, Output = Multiple<T>> + Copy + 'static,
+    }:
+    |self| self.factors().fold(*<Multiple<T> as Leaf>::UNIT, |product, t| product * crate::eval::Ref::eval(t))
+);
+
+crate::implement_grad!(
+    runtime T; const N: usize => Sum<T, N> >-> Sum<T::Differentiated, N>
+    where {
+        T: Grad<W>,
+        Self: Eval,
+        Sum<T::Differentiated, N>: Eval,
+    }:
+    |self, x| Sum(core::array::from_fn(|i| crate::grad::Ref::grad(&self.0[i], x)), self.1)
+);
+
+// Generalized product rule: (uvw...)' = u'vw... + uv'w... + uvw'... + ...
+// All in one node, so that the gradient of N factors takes O(N) space and time rather than O(N²).
+crate::implement_grad!(
+    runtime T; const N: usize => Product<T, N> >-> ProductRule<T, T::Differentiated, N>
+    where {
+        T: Grad<W> + Copy,
+        Self: Eval,
+        ProductRule<T, T::Differentiated, N>: Eval,
+    }:
+    |self, x| ProductRule(self.0, core::array::from_fn(|i| crate::grad::Ref::grad(&self.0[i], x)), self.1)
+);
+
+// Carries the product and the product rule so far, factor by factor: (p, d) -> (p * t, d * t + p * t').
+crate::implement_eval!(
+    runtime T, D; const N: usize => ProductRule<T, D, N> >-> Multiple<T>
+    where {
+        T: Eval<Evaluated: core::ops::Mul + Copy>,
+        D: Eval,
+        Multiple<T>: Leaf<Unit = &'static Multiple<T>>
+            + core::ops::Mul<T::Evaluated, Output = Multiple<T>>
+            + core::ops::Mul<D::Evaluated, Output = Multiple<T>>
+            + core::ops::Add<Output = Multiple<T>>
+            + Copy
+            + 'static,
+    }:
+    |self| self.pairs().fold((*<Multiple<T> as Leaf>::UNIT, *<Multiple<T> as Leaf>::ZERO), |(product, rule), (t, d)| {
+        let t = crate::eval::Ref::eval(t);
+        let d = <Multiple<T> as core::ops::Mul<D::Evaluated>>::mul(product, crate::eval::Ref::eval(d));
+        (<Multiple<T> as core::ops::Mul<T::Evaluated>>::mul(product, t), <Multiple<T> as core::ops::Mul<T::Evaluated>>::mul(rule, t) + d)
+    }).1
+);
+
+// (Σ t_i' Π_{j≠i} t_j)' = Σ t_i'' Π_{j≠i} t_j + Σ t_i' (Σ_{j≠i} t_j' Π_{k≠i,j} t_k):
+// the second term is a product rule over every factor but the i-th.
+crate::implement_grad!(
+    runtime T, D; const N: usize => ProductRule<T, D, N>
+        >-> Add<ProductRule<T, D::Differentiated, N>, Sum<Mul<D, ProductRule<T, T::Differentiated, N>>, N>>
+    where {
+        T: Grad<W, Differentiated: Copy> + Copy,
+        D: Grad<W> + Copy,
+        Self: Eval,
+        ProductRule<T, D::Differentiated, N>: Eval,
+        ProductRule<T, T::Differentiated, N>: Eval,
+        Mul<D, ProductRule<T, T::Differentiated, N>>: Eval,
+        Sum<Mul<D, ProductRule<T, T::Differentiated, N>>, N>: Eval,
+        Add<ProductRule<T, D::Differentiated, N>, Sum<Mul<D, ProductRule<T, T::Differentiated, N>>, N>>: Eval,
+    }:
+    |self, x| {
+        let grads = core::array::from_fn(|i| crate::grad::Ref::grad(&self.0[i], x));
+        Add(
+            ProductRule(self.0, core::array::from_fn(|i| crate::grad::Ref::grad(&self.1[i], x)), self.2),
+            Sum(
+                core::array::from_fn(|i| {
+                    let mut others = self.2;
+                    others[i] = false;
+                    Mul(self.1[i], ProductRule(self.0, grads, others))
+                }),
+                self.2,
+            ),
+        )
+    }
+);
@@ -0,0 +1,32 @@
+use super::*;
+
+/// Quotient `%` rounds toward zero, `(a - a % b) / b`: exact for integers and floats alike,
+/// and equal to `floor(a / b)` whenever `a / b` isn't negative.
+type Quotient<L, R> = Div<Sub<L, Rem<L, R>>, R>;
+
+// Remainder: (a % b)' = a' - q b', where q is the quotient above
+// Piecewise, like `%` itself: the derivative doesn't exist where q jumps (i.e. where b divides a).
+// Each side appears more than once, so both have to be `Copy`.
+crate::implement_grad!(
+    L, R => Rem<L, R> >-> <
+        L::Differentiated as collapse::Sub<<Quotient<L, R> as collapse::Mul<R::Differentiated>>::Output>
+    >::Output
+    where {
+        L: ~const Eval<Evaluated: ~const core::ops::Rem<R::Evaluated>>
+            + ~const Grad<
+                W,
+                Differentiated: ~const collapse::Sub<<Quotient<L, R> as collapse::Mul<R::Differentiated>>::Output>,
+            >
+            + Copy,
+        R: ~const Eval + ~const Grad<W> + Copy,
+        L::Evaluated: ~const core::ops::Sub<
+            <L::Evaluated as core::ops::Rem<R::Evaluated>>::Output,
+            Output: ~const core::ops::Div<R::Evaluated>,
+        >,
+        Quotient<L, R>: ~const collapse::Mul<R::Differentiated>,
+    }:
+    |self, x| collapse::Sub::sub(
+        self.0.grad(x),
+        collapse::Mul::mul(Div(Sub(self.0, Rem(self.0, self.1)), self.1), self.1.grad(x)),
+    )
+);
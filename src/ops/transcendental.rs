@@ -0,0 +1,332 @@
+//! Functions beyond `core::ops` (e.g. `var(&x).exp()` and `var(&x).sin()`), evaluated via `float::Float`.
+//! None of these can run at compile time, since Rust's floating-point functions aren't `const`.
+
+use super::*;
+use crate::float::Float;
+
+macro_rules! unary_fn {
+    ($Name:ident, $name:ident, $doc:literal) => {
+        #[derive(Clone, Copy, Debug)]
+        #[doc = $doc]
+        pub struct $Name<T: ~const Eval>(pub(crate) T);
+        impl<T: Eval> Dynamic for $Name<T> {}
+        impl<T: Eval<Evaluated: Float>> Expr<T> {
+            #[doc = $doc]
+            #[inline(always)]
+            pub fn $name(self) -> Expr<$Name<T>> {
+                Expr($Name(self.0))
+            }
+        }
+        $crate::implement_eval!(
+            runtime T => $Name<T> >-> <T::Evaluated as Float>::Value
+            where { T: Eval<Evaluated: Float> }:
+            |self| (&self.0).eval().$name()
+        );
+    };
+}
+
+unary_fn!(Exp, exp, "Exponential (e.g. `e^x`).");
+unary_fn!(Ln, ln, "Natural logarithm (e.g. `ln x`).");
+unary_fn!(Sqrt, sqrt, "Square root (e.g. `sqrt(x)`).");
+unary_fn!(Sin, sin, "Sine in radians (e.g. `sin x`).");
+unary_fn!(Cos, cos, "Cosine in radians (e.g. `cos x`).");
+unary_fn!(Tan, tan, "Tangent in radians (e.g. `tan x`).");
+unary_fn!(Asin, asin, "Inverse sine in radians (e.g. `asin x`).");
+unary_fn!(Acos, acos, "Inverse cosine in radians (e.g. `acos x`).");
+unary_fn!(Atan, atan, "Inverse tangent in radians (e.g. `atan x`).");
+unary_fn!(Sinh, sinh, "Hyperbolic sine (e.g. `sinh x`).");
+unary_fn!(Cosh, cosh, "Hyperbolic cosine (e.g. `cosh x`).");
+unary_fn!(Tanh, tanh, "Hyperbolic tangent (e.g. `tanh x`).");
+
+/// Power with a floating-point exponent (e.g. `x^y`).
+#[derive(Clone, Copy, Debug)]
+pub struct Powf<L: ~const Eval, R: ~const Eval>(pub(crate) L, pub(crate) R);
+impl<L: Eval, R: Eval> Dynamic for Powf<L, R> {}
+
+/// Product that is zero wherever its left factor is, even if its right is infinite.
+/// This is `u^v ln u` in the derivative of `u^v`, which would otherwise be `0 * -inf = NaN` at `u = 0`, where `0^v` is flat in `v`.
+/// Only its value is guarded: it differentiates like `Mul`.
+#[derive(Clone, Copy, Debug)]
+pub struct ZeroMul<L: ~const Eval, R: ~const Eval>(pub(crate) L, pub(crate) R);
+impl<L: Eval, R: Eval> Dynamic for ZeroMul<L, R> {}
+
+/// Four-quadrant inverse tangent of `y / x` in radians (e.g. `atan2(y, x)`).
+#[derive(Clone, Copy, Debug)]
+pub struct Atan2<L: ~const Eval, R: ~const Eval>(pub(crate) L, pub(crate) R);
+impl<L: Eval, R: Eval> Dynamic for Atan2<L, R> {}
+
+/// Power with an integral exponent known when the expression is built (e.g. `x^3`).
+#[derive(Clone, Copy, Debug)]
+pub struct Powi<T: ~const Eval>(pub(crate) T, pub(crate) i32);
+impl<T: Eval> Dynamic for Powi<T> {}
+
+/// `c u^n` for a constant `c` and an integral `n` (e.g. `3 x^2`), which is what `powi` differentiates to.
+/// The exponent is an `i64`, since differentiating `x^i32::MIN` takes it past the last `i32`.
+#[derive(Clone, Copy, Debug)]
+pub struct Monomial<T: ~const Eval, V>(pub(crate) V, pub(crate) T, pub(crate) i64);
+impl<T: Eval, V> Dynamic for Monomial<T, V> {}
+
+impl<T: Eval<Evaluated: Float>> Expr<T> {
+    /// Power with a floating-point exponent (e.g. `var(&x).powf(lit(0.5))`).
+    #[inline(always)]
+    pub fn powf<R: Eval<Evaluated: Float<Value = <T::Evaluated as Float>::Value>>>(
+        self,
+        n: Expr<R>,
+    ) -> Expr<Powf<T, R>> {
+        Expr(Powf(self.0, n.0))
+    }
+    /// Power with an integral exponent (e.g. `var(&x).powi(3)`).
+    #[inline(always)]
+    pub fn powi(self, n: i32) -> Expr<Powi<T>> {
+        Expr(Powi(self.0, n))
+    }
+    /// Four-quadrant inverse tangent of `self / x` in radians (e.g. `var(&y).atan2(var(&x))`).
+    #[inline(always)]
+    pub fn atan2<R: Eval<Evaluated: Float<Value = <T::Evaluated as Float>::Value>>>(
+        self,
+        x: Expr<R>,
+    ) -> Expr<Atan2<T, R>> {
+        Expr(Atan2(self.0, x.0))
+    }
+}
+
+crate::implement_eval!(
+    runtime L, R => Powf<L, R> >-> <L::Evaluated as Float>::Value
+    where { L: Eval<Evaluated: Float>, R: Eval<Evaluated: Float<Value = <L::Evaluated as Float>::Value>> }:
+    |self| (&self.0).eval().powf((&self.1).eval().value())
+);
+
+crate::implement_eval!(
+    runtime L, R => ZeroMul<L, R> >-> <L::Evaluated as Float>::Value
+    where {
+        L: Eval<Evaluated: Float>,
+        R: Eval<Evaluated: Float<Value = <L::Evaluated as Float>::Value>>,
+        <L::Evaluated as Float>::Value: core::ops::Mul<Output = <L::Evaluated as Float>::Value> + PartialEq,
+    }:
+    |self| {
+        let l = (&self.0).eval().value();
+        if l == L::Evaluated::from_i32(0) { l } else { l * (&self.1).eval().value() }
+    }
+);
+
+crate::implement_eval!(
+    runtime L, R => Atan2<L, R> >-> <L::Evaluated as Float>::Value
+    where {
+        L: Eval<Evaluated: Float>,
+        R: Eval<Evaluated: Float<Value = <L::Evaluated as Float>::Value>>,
+    }:
+    |self| (&self.0).eval().atan2((&self.1).eval().value())
+);
+
+crate::implement_eval!(
+    runtime T => Powi<T> >-> <T::Evaluated as Float>::Value
+    where { T: Eval<Evaluated: Float> }:
+    |self| (&self.0).eval().powi(self.1)
+);
+
+crate::implement_eval!(
+    runtime T, V => Monomial<T, V> >-> V
+    where { T: Eval<Evaluated: Float<Value = V>>, V: Float<Value = V> + core::ops::Mul<Output = V> }:
+    |self| self.0 * crate::float::powi_wide((&self.1).eval(), self.2)
+);
+
+/// Chain rule where the derivative of `f` is another function `g`: `f(u)' = g(u) u'`.
+macro_rules! chain_rule {
+    ($($Name:ident => $Deriv:ident),+) => {
+        $(
+            crate::implement_grad!(
+                runtime T => $Name<T> >-> <$Deriv<T> as collapse::Mul<T::Differentiated>>::Output
+                where { T: Eval<Evaluated: Float> + Grad<W> + Copy, $Deriv<T>: collapse::Mul<T::Differentiated> }:
+                |self, x| collapse::Mul::mul($Deriv(self.0), self.0.grad(x))
+            );
+        )+
+    };
+}
+
+chain_rule!(Exp => Exp, Sin => Cos, Sinh => Cosh, Cosh => Sinh);
+
+// Chain rule: (cos u)' = -sin u u'
+crate::implement_grad!(
+    runtime T => Cos<T> >-> <<Sin<T> as collapse::Mul<T::Differentiated>>::Output as collapse::Neg>::Output
+    where {
+        T: Eval<Evaluated: Float> + Grad<W> + Copy,
+        Sin<T>: collapse::Mul<T::Differentiated, Output: collapse::Neg>,
+    }:
+    |self, x| collapse::Neg::neg(collapse::Mul::mul(Sin(self.0), self.0.grad(x)))
+);
+
+/// Chain rule where the derivative of `f` is `1 / g^2`: `f(u)' = u' / (g(u) g(u))`.
+macro_rules! chain_rule_over_square {
+    ($($Name:ident => $Deriv:ident),+) => {
+        $(
+            crate::implement_grad!(
+                runtime T => $Name<T> >-> <T::Differentiated as collapse::Div<Mul<$Deriv<T>, $Deriv<T>>>>::Output
+                where {
+                    T: Eval<Evaluated: Float> + Grad<W, Differentiated: collapse::Div<Mul<$Deriv<T>, $Deriv<T>>>> + Copy,
+                    Mul<$Deriv<T>, $Deriv<T>>: Eval,
+                }:
+                |self, x| collapse::Div::div(self.0.grad(x), Mul($Deriv(self.0), $Deriv(self.0)))
+            );
+        )+
+    };
+}
+
+chain_rule_over_square!(Tan => Cos, Tanh => Cosh);
+
+// Chain rule: (ln u)' = u' / u
+crate::implement_grad!(
+    runtime T => Ln<T> >-> <T::Differentiated as collapse::Div<T>>::Output
+    where { T: Eval<Evaluated: Float> + Grad<W, Differentiated: collapse::Div<T>> + Copy }:
+    |self, x| collapse::Div::div(self.0.grad(x), self.0)
+);
+
+/// Floating-point type `T` evaluates to (e.g. `f64` for `&f64`).
+type ValueOf<T> = <<T as crate::eval::Typed>::Evaluated as Float>::Value;
+
+/// Floating-point constant of the same type as `T` evaluates to.
+type LitOf<T> = Lit<ValueOf<T>>;
+
+// Chain rule: (sqrt u)' = u' / (2 sqrt u)
+crate::implement_grad!(
+    runtime T => Sqrt<T> >-> <T::Differentiated as collapse::Div<Mul<LitOf<T>, Sqrt<T>>>>::Output
+    where {
+        T: Eval<Evaluated: Float> + Grad<W, Differentiated: collapse::Div<Mul<LitOf<T>, Sqrt<T>>>> + Copy,
+        Mul<LitOf<T>, Sqrt<T>>: Eval,
+    }:
+    |self, x| collapse::Div::div(self.0.grad(x), Mul(Lit(T::Evaluated::from_i32(2)), Sqrt(self.0)))
+);
+
+// Chain rule: (u^n)' = n u^(n - 1) u'
+crate::implement_grad!(
+    runtime T => Powi<T> >-> <Monomial<T, ValueOf<T>> as collapse::Mul<T::Differentiated>>::Output
+    where {
+        T: Eval<Evaluated: Float> + Grad<W> + Copy,
+        ValueOf<T>: core::ops::Mul<Output = ValueOf<T>>,
+        Monomial<T, ValueOf<T>>: collapse::Mul<T::Differentiated>,
+    }:
+    |self, x| collapse::Mul::mul(Monomial(T::Evaluated::from_i32(self.1), self.0, i64::from(self.1) - 1), self.0.grad(x))
+);
+
+// Chain rule: (c u^n)' = c n u^(n - 1) u'
+crate::implement_grad!(
+    runtime T, V => Monomial<T, V> >-> <Monomial<T, V> as collapse::Mul<T::Differentiated>>::Output
+    where {
+        T: Eval<Evaluated: Float<Value = V>> + Grad<W> + Copy,
+        V: Float<Value = V> + core::ops::Add<Output = V> + core::ops::Mul<Output = V>,
+        Monomial<T, V>: collapse::Mul<T::Differentiated>,
+    }:
+    |self, x| collapse::Mul::mul(Monomial(self.0 * crate::float::from_i64::<V>(self.2), self.1, self.2 - 1), self.1.grad(x))
+);
+
+/// `v u^(v - 1)`, the factor multiplying `u'` in the derivative of `u^v`.
+type PowerRule<L, R> = Mul<R, Powf<L, Sub<R, LitOf<L>>>>;
+
+/// `u^v ln u`, the factor multiplying `v'` in the derivative of `u^v` (zero at `u = 0`: see `ZeroMul`).
+type ExponentRule<L, R> = ZeroMul<Powf<L, R>, Ln<L>>;
+
+/// `v u^(v - 1) u' + u^v ln u v'`, the derivative of `u^v`.
+type PowfGrad<L, R, W> = <<PowerRule<L, R> as collapse::Mul<
+    <L as crate::grad::Typed<W>>::Differentiated,
+>>::Output as collapse::Add<
+    <<R as crate::grad::Typed<W>>::Differentiated as collapse::Mul<ExponentRule<L, R>>>::Output,
+>>::Output;
+
+// Chain rule: (u^v)' = v u^(v - 1) u' + u^v ln u v'
+// The `ln u` term only appears if `v` isn't statically constant, since e.g. `0^2` would otherwise have derivative `NaN`.
+crate::implement_grad!(
+    runtime L, R => Powf<L, R> >-> PowfGrad<L, R, W>
+    where {
+        L: Eval<Evaluated: Float> + Grad<W> + Copy,
+        R: Eval<Evaluated: Float<Value = ValueOf<L>>> + Grad<W> + Copy,
+        R::Evaluated: core::ops::Sub<ValueOf<L>, Output = ValueOf<L>> + core::ops::Mul<ValueOf<L>, Output = ValueOf<L>>,
+        ValueOf<L>: core::ops::Mul<Output = ValueOf<L>>,
+        PowerRule<L, R>: collapse::Mul<L::Differentiated>,
+        R::Differentiated: collapse::Mul<ExponentRule<L, R>>,
+        <PowerRule<L, R> as collapse::Mul<L::Differentiated>>::Output:
+            collapse::Add<<R::Differentiated as collapse::Mul<ExponentRule<L, R>>>::Output>,
+    }:
+    |self, x| collapse::Add::add(
+        collapse::Mul::mul(
+            Mul(self.1, Powf(self.0, Sub(self.1, Lit(L::Evaluated::from_i32(1))))),
+            self.0.grad(x),
+        ),
+        collapse::Mul::mul(self.1.grad(x), ZeroMul(Powf(self.0, self.1), Ln(self.0))),
+    )
+);
+
+// Product rule, as for `Mul`: only the value is guarded
+crate::implement_grad!(
+    runtime L, R => ZeroMul<L, R> >-> <Mul<L, R> as crate::grad::Typed<W>>::Differentiated
+    where { L: Eval + Copy, R: Eval + Copy, Self: Eval, Mul<L, R>: Grad<W> }:
+    |self, x| crate::grad::Own::grad(Mul(self.0, self.1), x)
+);
+
+/// `sqrt(1 - u^2)`, the denominator in the derivatives of `asin` and `acos`.
+type SqrtOneMinusSquare<T> = Sqrt<Sub<LitOf<T>, Mul<T, T>>>;
+
+// Chain rule: (asin u)' = u' / sqrt(1 - u^2)
+crate::implement_grad!(
+    runtime T => Asin<T> >-> <T::Differentiated as collapse::Div<SqrtOneMinusSquare<T>>>::Output
+    where {
+        T: Eval<Evaluated: Float> + Grad<W, Differentiated: collapse::Div<SqrtOneMinusSquare<T>>> + Copy,
+        T::Evaluated: core::ops::Mul<Output = ValueOf<T>>,
+        ValueOf<T>: core::ops::Sub<Output = ValueOf<T>>,
+    }:
+    |self, x| collapse::Div::div(
+        self.0.grad(x),
+        Sqrt(Sub(Lit(T::Evaluated::from_i32(1)), Mul(self.0, self.0))),
+    )
+);
+
+// Chain rule: (acos u)' = -u' / sqrt(1 - u^2)
+crate::implement_grad!(
+    runtime T => Acos<T> >-> <<T::Differentiated as collapse::Div<SqrtOneMinusSquare<T>>>::Output as collapse::Neg>::Output
+    where {
+        T: Eval<Evaluated: Float>
+            + Grad<W, Differentiated: collapse::Div<SqrtOneMinusSquare<T>, Output: collapse::Neg>>
+            + Copy,
+        T::Evaluated: core::ops::Mul<Output = ValueOf<T>>,
+        ValueOf<T>: core::ops::Sub<Output = ValueOf<T>>,
+    }:
+    |self, x| collapse::Neg::neg(collapse::Div::div(
+        self.0.grad(x),
+        Sqrt(Sub(Lit(T::Evaluated::from_i32(1)), Mul(self.0, self.0))),
+    ))
+);
+
+// Chain rule: (atan u)' = u' / (1 + u^2)
+crate::implement_grad!(
+    runtime T => Atan<T> >-> <T::Differentiated as collapse::Div<Add<LitOf<T>, Mul<T, T>>>>::Output
+    where {
+        T: Eval<Evaluated: Float> + Grad<W, Differentiated: collapse::Div<Add<LitOf<T>, Mul<T, T>>>> + Copy,
+        T::Evaluated: core::ops::Mul<Output = ValueOf<T>>,
+        ValueOf<T>: core::ops::Add<Output = ValueOf<T>>,
+    }:
+    |self, x| collapse::Div::div(self.0.grad(x), Add(Lit(T::Evaluated::from_i32(1)), Mul(self.0, self.0)))
+);
+
+/// `x y' - y x'`, the numerator in the derivative of `atan2(y, x)`.
+type Atan2Numerator<L, R, W> =
+    <<R as collapse::Mul<<L as crate::grad::Typed<W>>::Differentiated>>::Output as collapse::Sub<
+        <L as collapse::Mul<<R as crate::grad::Typed<W>>::Differentiated>>::Output,
+    >>::Output;
+
+// Chain rule: atan2(y, x)' = (x y' - y x') / (x^2 + y^2)
+crate::implement_grad!(
+    runtime L, R => Atan2<L, R> >-> <Atan2Numerator<L, R, W> as collapse::Div<Add<Mul<R, R>, Mul<L, L>>>>::Output
+    where {
+        L: Eval<Evaluated: Float> + Grad<W> + collapse::Mul<R::Differentiated> + Copy,
+        R: Eval<Evaluated: Float<Value = <L::Evaluated as Float>::Value>>
+            + Grad<W>
+            + collapse::Mul<L::Differentiated, Output: collapse::Sub<<L as collapse::Mul<R::Differentiated>>::Output>>
+            + Copy,
+        Atan2Numerator<L, R, W>: collapse::Div<Add<Mul<R, R>, Mul<L, L>>>,
+        L::Evaluated: core::ops::Mul<Output = ValueOf<L>>,
+        R::Evaluated: core::ops::Mul<Output = ValueOf<L>>,
+        ValueOf<L>: core::ops::Add<Output = ValueOf<L>>,
+    }:
+    |self, x| collapse::Div::div(
+        collapse::Sub::sub(collapse::Mul::mul(self.1, self.0.grad(x)), collapse::Mul::mul(self.0, self.1.grad(x))),
+        Add(Mul(self.1, self.1), Mul(self.0, self.0)),
+    )
+);
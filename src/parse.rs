@@ -0,0 +1,355 @@
+//! Parse formulas typed at runtime (e.g. `parse("sin(x) * y + 3", &[(&x, "x"), (&y, "y")])`) into `dynamic::DynExpr`s,
+//! which evaluate, differentiate, and combine with static expressions (e.g. `parsed * var(&z)`).
+//!
+//! Grammar, loosest-binding first (as printed by `Display`): `+ -`, `* / %`, prefix `-`, then `^` (right-associative);
+//! atoms are numbers, variables, parenthesized expressions, and calls: `exp ln sqrt sin cos tan asin acos atan sinh cosh tanh`
+//! of one argument, `pow atan2` of two. Integral exponents (`x^2`, `pow(x, 2)`) become `Powi`.
+//!
+//! Input is untrusted, so nesting (parentheses, calls, prefix `-`, and exponents) stops at `MAX_DEPTH` levels
+//! with `ErrorKind::TooDeep` instead of overflowing the stack.
+
+use crate::{
+    dynamic::{DynExpr, Function, Real},
+    float::Float,
+};
+use core::{fmt, ops::Range, str::FromStr};
+
+/// What went wrong while parsing.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ErrorKind {
+    /// Character that can't start any token.
+    UnexpectedChar(char),
+    /// Input ended in the middle of an expression.
+    UnexpectedEnd,
+    /// Something other than what the grammar allows here (e.g. `"`)`"`).
+    Expected(&'static str),
+    /// Identifier that isn't one of the variables passed in.
+    UnknownVariable,
+    /// Call to a function that doesn't exist.
+    UnknownFunction,
+    /// Call with the wrong number of arguments.
+    Arity {
+        /// Arguments the function takes.
+        expected: usize,
+        /// Arguments given.
+        found: usize,
+    },
+    /// Number the value type can't represent (see `FromStr`).
+    InvalidNumber,
+    /// Nested more than `MAX_DEPTH` levels deep.
+    TooDeep,
+}
+
+/// Deepest nesting `parse` accepts (parentheses, calls, prefix `-`, and exponents all count).
+pub const MAX_DEPTH: usize = 128;
+
+/// Parse error, with the byte range of the input it's about.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    /// Byte range of the offending input (empty at the end of the input if it ended early).
+    pub span: Range<usize>,
+    /// What went wrong.
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {c:?}"),
+            ErrorKind::UnexpectedEnd => f.write_str("unexpected end of input"),
+            ErrorKind::Expected(what) => write!(f, "expected {what}"),
+            ErrorKind::UnknownVariable => f.write_str("unknown variable"),
+            ErrorKind::UnknownFunction => f.write_str("unknown function"),
+            ErrorKind::Arity { expected, found } => {
+                write!(f, "expected {expected} argument(s) but found {found}")
+            }
+            ErrorKind::InvalidNumber => f.write_str("invalid number"),
+            ErrorKind::TooDeep => write!(f, "nested more than {MAX_DEPTH} levels deep"),
+        }?;
+        write!(f, " at {}..{}", self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse a formula over the named variables (e.g. `&[(&x, "x"), (&y, "y")]`), which it borrows like `expr::var`,
+/// so `grad(&x)` works on the result.
+#[inline]
+pub fn parse<'a, T: Real + Float<Value = T> + FromStr>(
+    source: &str,
+    vars: &[(&'a T, &str)],
+) -> Result<DynExpr<'a, T>, ParseError> {
+    let mut parser = Parser {
+        source,
+        pos: 0,
+        depth: 0,
+        vars,
+    };
+    let expr = parser.sum()?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => Err(parser.error_here(ErrorKind::Expected("an operator or the end of input"))),
+    }
+}
+
+/// Recursive-descent parser, one method per binding power.
+struct Parser<'s, 'v, 'a, T> {
+    source: &'s str,
+    pos: usize,
+    /// Current nesting (see `MAX_DEPTH`).
+    depth: usize,
+    vars: &'v [(&'a T, &'v str)],
+}
+
+impl<'a, T: Real + Float<Value = T> + FromStr> Parser<'_, '_, 'a, T> {
+    /// Next character, if any (without consuming it).
+    #[inline]
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    #[inline]
+    fn skip_whitespace(&mut self) {
+        let rest = &self.source[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `c` (after any whitespace) if it's next.
+    #[inline]
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        let next = self.peek() == Some(c);
+        if next {
+            self.pos += c.len_utf8();
+        }
+        next
+    }
+
+    /// Error about the next character (or the empty span at the end of the input).
+    fn error_here(&self, kind: ErrorKind) -> ParseError {
+        let len = self.peek().map_or(0, char::len_utf8);
+        ParseError {
+            span: self.pos..self.pos + len,
+            kind,
+        }
+    }
+
+    /// Parse one level deeper, inside whatever `span` opened (e.g. a parenthesis), up to `MAX_DEPTH`.
+    fn nested(
+        &mut self,
+        span: Range<usize>,
+        parse: fn(&mut Self) -> Result<DynExpr<'a, T>, ParseError>,
+    ) -> Result<DynExpr<'a, T>, ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ParseError {
+                span,
+                kind: ErrorKind::TooDeep,
+            });
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    /// `term (("+" | "-") term)*`
+    fn sum(&mut self) -> Result<DynExpr<'a, T>, ParseError> {
+        let mut expr = self.term()?;
+        loop {
+            if self.eat('+') {
+                expr = DynExpr::Add(Box::new(expr), Box::new(self.term()?));
+            } else if self.eat('-') {
+                expr = DynExpr::Sub(Box::new(expr), Box::new(self.term()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// `prefix (("*" | "/" | "%") prefix)*`
+    fn term(&mut self) -> Result<DynExpr<'a, T>, ParseError> {
+        let mut expr = self.prefix()?;
+        loop {
+            if self.eat('*') {
+                expr = DynExpr::Mul(Box::new(expr), Box::new(self.prefix()?));
+            } else if self.eat('/') {
+                expr = DynExpr::Div(Box::new(expr), Box::new(self.prefix()?));
+            } else if self.eat('%') {
+                expr = DynExpr::Rem(Box::new(expr), Box::new(self.prefix()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// `"-" prefix | power`
+    fn prefix(&mut self) -> Result<DynExpr<'a, T>, ParseError> {
+        if self.eat('-') {
+            let operand = self.nested(self.pos - 1..self.pos, Self::prefix)?;
+            Ok(DynExpr::Neg(Box::new(operand)))
+        } else {
+            self.power()
+        }
+    }
+
+    /// `atom ("^" prefix)?`
+    fn power(&mut self) -> Result<DynExpr<'a, T>, ParseError> {
+        let base = self.atom()?;
+        if !self.eat('^') {
+            return Ok(base);
+        }
+        let caret = self.pos - 1..self.pos;
+        match self.integer() {
+            Some(n) => Ok(DynExpr::Powi(Box::new(base), n)),
+            None => {
+                let exponent = self.nested(caret, Self::prefix)?;
+                Ok(DynExpr::Powf(Box::new(base), Box::new(exponent)))
+            }
+        }
+    }
+
+    /// Integral exponent (e.g. the `-2` in `x^-2`), consumed only if it's all there is to the exponent.
+    fn integer(&mut self) -> Option<i32> {
+        self.skip_whitespace();
+        let rest = &self.source[self.pos..];
+        let digits = rest.strip_prefix('-').unwrap_or(rest);
+        let end = rest.len() - digits.len() + digits.bytes().take_while(u8::is_ascii_digit).count();
+        let after = rest[end..].trim_start().chars().next();
+        // Not integral if it continues as a float (`2.5`, `2e3`) or a larger expression at the same binding power (`2^3`)
+        if end == rest.len() - digits.len()
+            || matches!(after, Some('.' | 'e' | 'E' | '^'))
+            || after.map_or(false, char::is_alphanumeric)
+        {
+            return None;
+        }
+        let n = rest[..end].parse().ok()?;
+        self.pos += end;
+        Some(n)
+    }
+
+    /// Number, variable, call, or parenthesized expression.
+    fn atom(&mut self) -> Result<DynExpr<'a, T>, ParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let rest = &self.source[start..];
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.nested(start..self.pos, Self::sum)?;
+                self.close()?;
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let mut end = rest
+                    .bytes()
+                    .take_while(|b| b.is_ascii_digit() || *b == b'.')
+                    .count();
+                // Exponent, e.g. `1e-3`
+                if let Some(exponent) = rest[end..].strip_prefix(['e', 'E']) {
+                    let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+                    let count = digits.bytes().take_while(u8::is_ascii_digit).count();
+                    if count > 0 {
+                        end += 1 + exponent.len() - digits.len() + count;
+                    }
+                }
+                self.pos += end;
+                rest[..end]
+                    .parse()
+                    .map(DynExpr::Lit)
+                    .map_err(|_| ParseError {
+                        span: start..self.pos,
+                        kind: ErrorKind::InvalidNumber,
+                    })
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let end = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                let name = &rest[..end];
+                self.pos += end;
+                let span = start..self.pos;
+                if self.eat('(') {
+                    self.call(name, span)
+                } else {
+                    match self.vars.iter().find(|&&(_, var)| var == name) {
+                        Some(&(x, _)) => Ok(DynExpr::Var(x, None)),
+                        None => Err(ParseError {
+                            span,
+                            kind: ErrorKind::UnknownVariable,
+                        }),
+                    }
+                }
+            }
+            Some(c) if "+*/%^)".contains(c) || c == ',' => {
+                Err(self.error_here(ErrorKind::Expected("an expression")))
+            }
+            Some(c) => Err(self.error_here(ErrorKind::UnexpectedChar(c))),
+            None => Err(self.error_here(ErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    /// Consume a closing parenthesis.
+    fn close(&mut self) -> Result<(), ParseError> {
+        if self.eat(')') {
+            Ok(())
+        } else {
+            Err(self.error_here(ErrorKind::Expected("`)`")))
+        }
+    }
+
+    /// Arguments (after the opening parenthesis) of a call to `name`, spanning `span`.
+    fn call(&mut self, name: &str, span: Range<usize>) -> Result<DynExpr<'a, T>, ParseError> {
+        let function = Function::ALL.into_iter().find(|f| f.name() == name);
+        let expected = match (name, function) {
+            ("pow" | "atan2", _) => 2,
+            (_, Some(_)) => 1,
+            (_, None) => {
+                return Err(ParseError {
+                    span,
+                    kind: ErrorKind::UnknownFunction,
+                })
+            }
+        };
+        let mut args = Vec::with_capacity(expected);
+        let mut exponent = None;
+        if !self.eat(')') {
+            loop {
+                // `pow(x, 2)` is how `Display` prints `Powi`
+                if name == "pow" && args.len() == 1 {
+                    let before = self.pos;
+                    match self.integer() {
+                        Some(n) if self.eat(')') => {
+                            exponent = Some(n);
+                            args.push(DynExpr::Lit(<T as Real>::from_i32(n)));
+                            break;
+                        }
+                        _ => self.pos = before,
+                    }
+                }
+                args.push(self.nested(span.clone(), Self::sum)?);
+                if !self.eat(',') {
+                    self.close()?;
+                    break;
+                }
+            }
+        }
+        if args.len() != expected {
+            return Err(ParseError {
+                span: span.start..self.pos,
+                kind: ErrorKind::Arity {
+                    expected,
+                    found: args.len(),
+                },
+            });
+        }
+        let mut args = args.into_iter().map(Box::new);
+        let mut arg = || args.next().expect("arity already checked");
+        Ok(match (function, exponent) {
+            (Some(f), _) => DynExpr::Call(f, arg()),
+            (None, Some(n)) => DynExpr::Powi(arg(), n),
+            (None, None) if name == "pow" => DynExpr::Powf(arg(), arg()),
+            (None, None) => DynExpr::Atan2(arg(), arg()),
+        })
+    }
+}
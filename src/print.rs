@@ -0,0 +1,710 @@
+//! Human-readable output: `Display` for `Expr` renders minimal-parenthesis infix math (e.g. `-x + 2*y`),
+//! so gradients built by `grad` can be logged and inspected.
+//!
+//! Variables print as their current value unless named (e.g. `expr.named(&[(&x, "x"), (&y, "y")])`).
+//! Every node describes itself through `Node::shape`, so other output formats can walk the same trees.
+
+use crate::{
+    eval::{self, Eval},
+    expr::Expr,
+    leaf::{Delta, Leaf, Lit, One, Var, Zero},
+    ops,
+};
+use core::fmt::{self, Display, Formatter, Write};
+
+/// Unary operation or function.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Unary {
+    /// `-x`
+    Neg,
+    /// `!x`
+    Not,
+    /// `exp(x)`
+    Exp,
+    /// `ln(x)`
+    Ln,
+    /// `sqrt(x)`
+    Sqrt,
+    /// `sin(x)`
+    Sin,
+    /// `cos(x)`
+    Cos,
+    /// `tan(x)`
+    Tan,
+    /// `asin(x)`
+    Asin,
+    /// `acos(x)`
+    Acos,
+    /// `atan(x)`
+    Atan,
+    /// `sinh(x)`
+    Sinh,
+    /// `cosh(x)`
+    Cosh,
+    /// `tanh(x)`
+    Tanh,
+}
+
+/// Binary operation or function.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Binary {
+    /// `a + b`
+    Add,
+    /// `a - b`
+    Sub,
+    /// `a*b`
+    Mul,
+    /// `a/b`
+    Div,
+    /// `a % b`
+    Rem,
+    /// `a << b`
+    Shl,
+    /// `a >> b`
+    Shr,
+    /// `a & b`
+    BitAnd,
+    /// `a | b`
+    BitOr,
+    /// `a ^ b`
+    BitXor,
+    /// `pow(a, b)`
+    Powf,
+    /// `atan2(a, b)`
+    Atan2,
+}
+
+impl Unary {
+    /// Name of the operation (e.g. `"Neg"`) or function (e.g. `"sin"`).
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Unary::Neg => "Neg",
+            Unary::Not => "Not",
+            Unary::Exp => "exp",
+            Unary::Ln => "ln",
+            Unary::Sqrt => "sqrt",
+            Unary::Sin => "sin",
+            Unary::Cos => "cos",
+            Unary::Tan => "tan",
+            Unary::Asin => "asin",
+            Unary::Acos => "acos",
+            Unary::Atan => "atan",
+            Unary::Sinh => "sinh",
+            Unary::Cosh => "cosh",
+            Unary::Tanh => "tanh",
+        }
+    }
+}
+
+impl Binary {
+    /// Name of the operation (e.g. `"Add"`) or function (e.g. `"pow"`).
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Binary::Add => "Add",
+            Binary::Sub => "Sub",
+            Binary::Mul => "Mul",
+            Binary::Div => "Div",
+            Binary::Rem => "Rem",
+            Binary::Shl => "Shl",
+            Binary::Shr => "Shr",
+            Binary::BitAnd => "BitAnd",
+            Binary::BitOr => "BitOr",
+            Binary::BitXor => "BitXor",
+            Binary::Powf => "pow",
+            Binary::Atan2 => "atan2",
+        }
+    }
+    /// Infix operator and binding power (higher binds tighter), or `None` for functions.
+    #[inline]
+    pub const fn infix(self) -> Option<(&'static str, u8)> {
+        match self {
+            Binary::BitOr => Some((" | ", 1)),
+            Binary::BitXor => Some((" ^ ", 2)),
+            Binary::BitAnd => Some((" & ", 3)),
+            Binary::Shl => Some((" << ", 4)),
+            Binary::Shr => Some((" >> ", 4)),
+            Binary::Add => Some((" + ", 5)),
+            Binary::Sub => Some((" - ", 5)),
+            Binary::Mul => Some(("*", 6)),
+            Binary::Div => Some(("/", 6)),
+            Binary::Rem => Some((" % ", 6)),
+            Binary::Powf | Binary::Atan2 => None,
+        }
+    }
+}
+
+/// What a node is, with its children (if any) as `Node`s in turn.
+#[derive(Clone, Copy)]
+pub enum Shape<'a> {
+    /// Variable at `ptr` (tagged `id`, if any), currently holding `value`.
+    Var {
+        /// Address, i.e. identity.
+        ptr: *const (),
+        /// ID from `expr::var_id`, if any.
+        id: Option<usize>,
+        /// Current value.
+        value: &'a dyn Display,
+    },
+    /// Constant (`Lit`, `Delta`, `Zero`, or `One`).
+    Const(&'a dyn Display),
+    /// Unary operation or function.
+    Unary(Unary, &'a dyn Node),
+    /// Binary operation or function.
+    Binary(Binary, &'a dyn Node, &'a dyn Node),
+    /// `pow(x, n)` for integral `n`.
+    Powi(&'a dyn Node, i32),
+    /// Flat sum (`Binary::Add`) or product (`Binary::Mul`) of any number of operands, e.g. `ops::Sum`.
+    Nary(Binary, &'a dyn Operands),
+}
+
+/// Operands of an n-ary node (see `Shape::Nary`).
+/// Operands are visited rather than returned, so a node can lend out views it builds on the fly (e.g. `ops::ProductRule`).
+pub trait Operands {
+    /// Call `f` on every operand, in order, stopping at the first error.
+    fn try_for_each(&self, f: &mut dyn FnMut(&dyn Node) -> fmt::Result) -> fmt::Result;
+}
+
+/// Whether an n-ary node has no operands.
+#[inline]
+pub(crate) fn is_empty(nary: &dyn Operands) -> bool {
+    nary.try_for_each(&mut |_| Err(fmt::Error)).is_ok()
+}
+
+/// What an n-ary node with no operands evaluates to: `0` for sums, `1` for products.
+#[inline]
+pub(crate) const fn identity(op: Binary) -> u8 {
+    match op {
+        Binary::Mul => 1,
+        _ => 0,
+    }
+}
+
+/// Expression node that can describe itself (see `Shape`).
+pub trait Node {
+    /// What this node is.
+    fn shape(&self) -> Shape<'_>;
+    /// Print what this node evaluates to (evaluating its whole subtree).
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result;
+    /// Name of the (owned) type this node evaluates to, e.g. `"f64"`.
+    fn value_type(&self) -> &'static str;
+}
+
+/// Names for variables, looked up by address.
+pub trait Names {
+    /// Name of the variable at `ptr`, if any.
+    fn name(&self, ptr: *const ()) -> Option<&str>;
+}
+
+impl Names for () {
+    #[inline(always)]
+    fn name(&self, _ptr: *const ()) -> Option<&str> {
+        None
+    }
+}
+
+impl<T> Names for [(&T, &str)] {
+    #[inline]
+    fn name(&self, ptr: *const ()) -> Option<&str> {
+        self.iter()
+            .find(|(x, _)| core::ptr::eq(*x as *const T as *const (), ptr))
+            .map(|&(_, name)| name)
+    }
+}
+
+impl<T, const N: usize> Names for [(&T, &str); N] {
+    #[inline(always)]
+    fn name(&self, ptr: *const ()) -> Option<&str> {
+        self[..].name(ptr)
+    }
+}
+
+/// Expression displayed with named variables (see `Expr::named`).
+pub struct Named<'a, T: ?Sized, N: ?Sized> {
+    pub(crate) expr: &'a T,
+    pub(crate) names: &'a N,
+}
+
+impl<T: Eval + Node> Expr<T> {
+    /// Display with variables named (e.g. `expr.named(&[(&x, "x"), (&y, "y")])`) instead of printed as their values.
+    #[inline(always)]
+    pub fn named<'a, N: Names + ?Sized>(&'a self, names: &'a N) -> Named<'a, Self, N> {
+        Named { expr: self, names }
+    }
+}
+
+impl<T: Node + ?Sized, N: Names + ?Sized> Display for Named<'_, T, N> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        infix(self.expr, self.names, 0, f)
+    }
+}
+
+impl<T: Eval + Node> Display for Expr<T> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        infix(&self.0, &(), 0, f)
+    }
+}
+
+/// Binding power of prefix operators: tighter than any infix operator.
+pub(crate) const PREFIX: u8 = 7;
+/// Binding power of variables, nonnegative constants, and function calls.
+pub(crate) const ATOM: u8 = 8;
+
+/// Whether a value displays with a leading minus sign (so it binds like a prefix operator).
+pub(crate) fn is_negative(value: &dyn Display) -> bool {
+    struct First(Option<char>);
+    impl Write for First {
+        #[inline]
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            if self.0.is_none() {
+                self.0 = s.chars().next();
+            }
+            Ok(())
+        }
+    }
+    let mut first = First(None);
+    let _ = write!(first, "{value}");
+    first.0 == Some('-')
+}
+
+/// Binding power of a node as printed in infix.
+fn binding<N: Names + ?Sized>(node: &(impl Node + ?Sized), names: &N) -> u8 {
+    match node.shape() {
+        Shape::Var { ptr, .. } if names.name(ptr).is_some() => ATOM,
+        Shape::Var { value, .. } | Shape::Const(value) if is_negative(value) => PREFIX,
+        Shape::Var { .. } | Shape::Const(_) | Shape::Powi(..) => ATOM,
+        Shape::Unary(Unary::Neg | Unary::Not, _) => PREFIX,
+        Shape::Unary(..) => ATOM,
+        Shape::Binary(op, ..) => op.infix().map_or(ATOM, |(_, power)| power),
+        Shape::Nary(_, nary) if is_empty(nary) => ATOM,
+        Shape::Nary(op, _) => op.infix().map_or(ATOM, |(_, power)| power),
+    }
+}
+
+/// Print `node`, parenthesized if it binds less tightly than `min`.
+fn infix<N: Names + ?Sized>(
+    node: &(impl Node + ?Sized),
+    names: &N,
+    min: u8,
+    f: &mut Formatter<'_>,
+) -> fmt::Result {
+    if binding(node, names) < min {
+        f.write_char('(')?;
+        infix(node, names, 0, f)?;
+        return f.write_char(')');
+    }
+    match node.shape() {
+        Shape::Var { ptr, value, .. } => match names.name(ptr) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{value}"),
+        },
+        Shape::Const(value) => write!(f, "{value}"),
+        Shape::Unary(op @ (Unary::Neg | Unary::Not), x) => {
+            // `-(-x)` rather than `--x`
+            f.write_char(if op == Unary::Neg { '-' } else { '!' })?;
+            infix(x, names, if op == Unary::Neg { ATOM } else { PREFIX }, f)
+        }
+        Shape::Unary(op, x) => {
+            write!(f, "{}(", op.name())?;
+            infix(x, names, 0, f)?;
+            f.write_char(')')
+        }
+        Shape::Binary(op, a, b) => match op.infix() {
+            // Left-associative: only the right operand needs parentheses at equal binding power
+            Some((symbol, power)) => {
+                infix(a, names, power, f)?;
+                f.write_str(symbol)?;
+                infix(b, names, power + 1, f)
+            }
+            None => {
+                write!(f, "{}(", op.name())?;
+                infix(a, names, 0, f)?;
+                f.write_str(", ")?;
+                infix(b, names, 0, f)?;
+                f.write_char(')')
+            }
+        },
+        Shape::Powi(x, n) => {
+            f.write_str("pow(")?;
+            infix(x, names, 0, f)?;
+            write!(f, ", {n})")
+        }
+        Shape::Nary(op, nary) => {
+            let (symbol, power) = op.infix().unwrap_or_default();
+            let mut empty = true;
+            nary.try_for_each(&mut |x| {
+                if !empty {
+                    f.write_str(symbol)?;
+                }
+                infix(x, names, if empty { power } else { power + 1 }, f)?;
+                empty = false;
+                Ok(())
+            })?;
+            if empty {
+                write!(f, "{}", identity(op))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Type name without any leading reference (e.g. `"f64"` for `&'static f64`, as `Delta` evaluates to).
+fn owned_type_name<T>() -> &'static str {
+    let name = core::any::type_name::<T>();
+    name.strip_prefix('&').unwrap_or(name)
+}
+
+// Leaves
+
+impl<T: Leaf + Display> Node for &T {
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Var {
+            ptr: *self as *const T as *const (),
+            id: None,
+            value: *self,
+        }
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(*self, f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+}
+
+impl<T: Leaf + Display, const ID: usize> Node for Var<ID, &T> {
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Var {
+            ptr: self.0 as *const T as *const (),
+            id: Some(ID),
+            value: self.0,
+        }
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self.0, f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+}
+
+impl<T: Leaf + Display> Node for Lit<T> {
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Const(&self.0)
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+}
+
+impl<T: Leaf<Unit = &'static T> + Display + 'static> Node for Delta<T> {
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Const(if self.0 { T::UNIT } else { T::ZERO })
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(if self.0 { T::UNIT } else { T::ZERO }, f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+}
+
+impl<T: Leaf<Unit = &'static T> + Display + 'static> Node for Zero<T> {
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Const(T::ZERO)
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(T::ZERO, f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+}
+
+impl<T: Leaf<Unit = &'static T> + Display + 'static> Node for One<T> {
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Const(T::UNIT)
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(T::UNIT, f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+}
+
+impl<T: Eval + Node> Node for Expr<T> {
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        self.0.shape()
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt_value(f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        self.0.value_type()
+    }
+}
+
+// Operations
+
+macro_rules! node_unary {
+    ($($Name:ident),+) => {
+        $(
+            impl<T: Eval + Node> Node for ops::$Name<T>
+            where
+                Self: eval::Ref<Evaluated: Display>,
+            {
+                #[inline(always)]
+                fn shape(&self) -> Shape<'_> {
+                    Shape::Unary(Unary::$Name, &self.0)
+                }
+                #[inline(always)]
+                fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                    eval::Ref::eval(self).fmt(f)
+                }
+                #[inline(always)]
+                fn value_type(&self) -> &'static str {
+                    owned_type_name::<<Self as eval::Typed>::Evaluated>()
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! node_binary {
+    ($($Name:ident),+) => {
+        $(
+            impl<L: Eval + Node, R: Eval + Node> Node for ops::$Name<L, R>
+            where
+                Self: eval::Ref<Evaluated: Display>,
+            {
+                #[inline(always)]
+                fn shape(&self) -> Shape<'_> {
+                    Shape::Binary(Binary::$Name, &self.0, &self.1)
+                }
+                #[inline(always)]
+                fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                    eval::Ref::eval(self).fmt(f)
+                }
+                #[inline(always)]
+                fn value_type(&self) -> &'static str {
+                    owned_type_name::<<Self as eval::Typed>::Evaluated>()
+                }
+            }
+        )+
+    };
+}
+
+node_unary!(Neg, Not, Exp, Ln, Sqrt, Sin, Cos, Tan, Asin, Acos, Atan, Sinh, Cosh, Tanh);
+node_binary!(Add, Sub, Mul, Div, Rem, Shl, Shr, BitAnd, BitOr, BitXor, Powf, Atan2);
+
+// Prints as the product it is (the guard against `0 * inf` is only in its value)
+impl<L: Eval + Node, R: Eval + Node> Node for ops::ZeroMul<L, R>
+where
+    Self: eval::Ref<Evaluated: Display>,
+{
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Binary(Binary::Mul, &self.0, &self.1)
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        eval::Ref::eval(self).fmt(f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        owned_type_name::<<Self as eval::Typed>::Evaluated>()
+    }
+}
+
+impl<T: Eval + Node> Node for ops::Powi<T>
+where
+    Self: eval::Ref<Evaluated: Display>,
+{
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Powi(&self.0, self.1)
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        eval::Ref::eval(self).fmt(f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        owned_type_name::<<Self as eval::Typed>::Evaluated>()
+    }
+}
+
+// `c*pow(u, n)`, with the power split in two if its exponent doesn't fit an `i32` (see `float::split_exponent`)
+impl<T: Eval + Node + Copy, V: Leaf + Display + Copy> Operands for ops::Monomial<T, V>
+where
+    ops::Powi<T>: eval::Ref<Evaluated: Display>,
+{
+    #[inline]
+    fn try_for_each(&self, f: &mut dyn FnMut(&dyn Node) -> fmt::Result) -> fmt::Result {
+        f(&Lit(self.0))?;
+        let (a, b) = crate::float::split_exponent(self.2);
+        f(&ops::Powi(self.1, a))?;
+        b.map_or(Ok(()), |b| f(&ops::Powi(self.1, b)))
+    }
+}
+
+impl<T: Eval + Node + Copy, V: Leaf + Display + Copy> Node for ops::Monomial<T, V>
+where
+    Self: eval::Ref<Evaluated: Display>,
+    ops::Powi<T>: eval::Ref<Evaluated: Display>,
+{
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Nary(Binary::Mul, self)
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        eval::Ref::eval(self).fmt(f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        owned_type_name::<<Self as eval::Typed>::Evaluated>()
+    }
+}
+
+macro_rules! node_nary {
+    ($($Name:ident: $Op:ident, $operands:ident),+) => {
+        $(
+            impl<T: Eval + Node, const N: usize> Operands for ops::$Name<T, N> {
+                #[inline]
+                fn try_for_each(&self, f: &mut dyn FnMut(&dyn Node) -> fmt::Result) -> fmt::Result {
+                    self.$operands().try_for_each(|x| f(x))
+                }
+            }
+
+            impl<T: Eval + Node, const N: usize> Node for ops::$Name<T, N>
+            where
+                Self: eval::Ref<Evaluated: Display>,
+            {
+                #[inline(always)]
+                fn shape(&self) -> Shape<'_> {
+                    Shape::Nary(Binary::$Op, self)
+                }
+                #[inline(always)]
+                fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                    eval::Ref::eval(self).fmt(f)
+                }
+                #[inline(always)]
+                fn value_type(&self) -> &'static str {
+                    owned_type_name::<<Self as eval::Typed>::Evaluated>()
+                }
+            }
+        )+
+    };
+}
+
+node_nary!(Sum: Add, terms, Product: Mul, factors);
+
+impl<T: Eval + Node + Copy, D: Eval + Node + Copy, const N: usize> Operands
+    for ops::ProductRule<T, D, N>
+where
+    ops::Product<T, N>: Eval,
+    ops::Mul<D, ops::Product<T, N>>: eval::Ref<Evaluated: Display>,
+{
+    #[inline]
+    fn try_for_each(&self, f: &mut dyn FnMut(&dyn Node) -> fmt::Result) -> fmt::Result {
+        (0..N)
+            .filter(|&i| self.2[i])
+            .try_for_each(|i| f(&Term(self, i)))
+    }
+}
+
+impl<T: Eval + Node + Copy, D: Eval + Node + Copy, const N: usize> Node
+    for ops::ProductRule<T, D, N>
+where
+    Self: eval::Ref<Evaluated: Display>,
+    ops::Product<T, N>: Eval,
+    ops::Mul<D, ops::Product<T, N>>: eval::Ref<Evaluated: Display>,
+{
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Nary(Binary::Add, self)
+    }
+    #[inline(always)]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        eval::Ref::eval(self).fmt(f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        owned_type_name::<<Self as eval::Typed>::Evaluated>()
+    }
+}
+
+/// Term `i` of a product rule: the gradient of factor `i` times every other factor.
+struct Term<'a, T: Eval, D: Eval, const N: usize>(&'a ops::ProductRule<T, D, N>, usize);
+
+impl<T: Eval + Node + Copy, D: Eval + Node + Copy, const N: usize> Term<'_, T, D, N> {
+    /// The other factors.
+    #[inline]
+    fn others(&self) -> impl Iterator<Item = &T> {
+        let Self(rule, i) = *self;
+        rule.0
+            .iter()
+            .zip(rule.2)
+            .enumerate()
+            .filter_map(move |(j, (t, keep))| (keep && j != i).then_some(t))
+    }
+}
+
+impl<T: Eval + Node + Copy, D: Eval + Node + Copy, const N: usize> Operands for Term<'_, T, D, N> {
+    #[inline]
+    fn try_for_each(&self, f: &mut dyn FnMut(&dyn Node) -> fmt::Result) -> fmt::Result {
+        f(&self.0 .1[self.1])?;
+        self.others().try_for_each(|x| f(x))
+    }
+}
+
+impl<T: Eval + Node + Copy, D: Eval + Node + Copy, const N: usize> Node for Term<'_, T, D, N>
+where
+    ops::Product<T, N>: Eval,
+    ops::Mul<D, ops::Product<T, N>>: eval::Ref<Evaluated: Display>,
+{
+    #[inline(always)]
+    fn shape(&self) -> Shape<'_> {
+        Shape::Nary(Binary::Mul, self)
+    }
+    #[inline]
+    fn fmt_value(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self(rule, i) = *self;
+        let mut others = rule.2;
+        others[i] = false;
+        eval::Ref::eval(&ops::Mul(rule.1[i], ops::Product(rule.0, others))).fmt(f)
+    }
+    #[inline(always)]
+    fn value_type(&self) -> &'static str {
+        owned_type_name::<<ops::Mul<D, ops::Product<T, N>> as eval::Typed>::Evaluated>()
+    }
+}
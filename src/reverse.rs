@@ -0,0 +1,564 @@
+//! Reverse-mode automatic differentation: evaluate once, then push adjoints (`d output / d node`) back down the tree,
+//! collecting the gradient with respect to every variable at once (e.g. `expr.backward().wrt(&x)`).
+//! `grad` needs one pass per variable; this needs one pass total, which is what you want for a loss over many parameters.
+//!
+//! The same pass also runs on `forward::Dual`s, seeded like `forward::Forward` (e.g. `expr.backward_along(direction(&vars, &v))`):
+//! every adjoint then carries its derivative along the seed, so one pass yields a Hessian-vector product (forward over reverse, see `grad::hvp`).
+//!
+//! Bitwise operations aren't supported: their Boolean difference has a cross term (`du dv`) that can't be split into per-input adjoints.
+//! Use `grad` or `forward::Forward` for those.
+
+use crate::{
+    eval::Eval,
+    expr::Expr,
+    float::Float,
+    forward::{Dual, Seed},
+    leaf::{Delta, Leaf, Lit, One, Var, Zero},
+    ops,
+};
+use core::borrow::Borrow;
+use std::collections::HashMap;
+
+/// Gradient of an expression with respect to each of its variables, keyed by address (i.e. by variable identity).
+#[derive(Clone, Debug)]
+pub struct Gradients<T> {
+    map: HashMap<*const (), T>,
+}
+
+impl<T> Default for Gradients<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Gradients {
+            map: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Copy> Gradients<T> {
+    /// Gradient with respect to the variable at `x`, if it appears in the expression at all.
+    #[inline(always)]
+    pub fn get(&self, x: &T) -> Option<T> {
+        self.map.get(&(x as *const T as *const ())).copied()
+    }
+    /// Gradient with respect to the variable at `x`: `Leaf::ZERO` if it doesn't appear in the expression.
+    #[inline(always)]
+    pub fn wrt(&self, x: &T) -> T
+    where
+        T: Leaf<Unit = &'static T> + 'static,
+    {
+        self.get(x).unwrap_or(*T::ZERO)
+    }
+    /// Number of distinct variables in the expression.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    /// Whether the expression had no variables at all.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// Add an adjoint flowing into the variable at `x` (variables used more than once sum their adjoints).
+    #[inline(always)]
+    fn accumulate<X>(&mut self, x: &X, adjoint: T)
+    where
+        T: core::ops::Add<Output = T>,
+    {
+        self.map
+            .entry(x as *const X as *const ())
+            .and_modify(|sum| *sum = *sum + adjoint)
+            .or_insert(adjoint);
+    }
+}
+
+impl<T: Leaf<Unit = &'static T> + Copy + 'static> Gradients<Dual<T>> {
+    /// Gradient with respect to the variable at `x` (a plain value, unlike `get`) and its derivative along the seed:
+    /// zero if `x` doesn't appear in the expression.
+    #[inline(always)]
+    pub fn wrt_dual(&self, x: &T) -> Dual<T> {
+        self.map
+            .get(&(x as *const T as *const ()))
+            .copied()
+            .unwrap_or(Dual::ZERO)
+    }
+}
+
+/// Differentiate an expression with respect to all its variables in one forward and one reverse pass.
+/// `W` seeds the leaves: `()` computes on plain values (see `Gradient`), and a `forward::Seed` on `Dual`s (see `backward_along`).
+pub trait Backward<W: Copy = ()>: Eval {
+    /// Value of every node, adjoint, and gradient: the owned value (e.g. `f64` for `&f64`), or its `Dual` when seeded.
+    type Value: Copy;
+    /// Intermediate values the reverse pass needs, saved by the forward pass.
+    type Tape;
+    /// Forward pass: evaluate, saving intermediate values.
+    fn record(&self, seed: W) -> (Self::Value, Self::Tape);
+    /// Reverse pass: given this node's value and adjoint (`d output / d self`), push adjoints down to every leaf.
+    fn propagate(
+        &self,
+        value: Self::Value,
+        tape: &Self::Tape,
+        adjoint: Self::Value,
+        grads: &mut Gradients<Self::Value>,
+    );
+    /// Gradient with respect to every variable at once, each with its derivative along `seed` (see `Gradients::wrt_dual`):
+    /// seeded with a `forward::Direction`, that's a Hessian-vector product in one pass.
+    #[inline(always)]
+    fn backward_along(&self, seed: W) -> Gradients<Self::Value>
+    where
+        Self::Value: Leaf<Unit = Self::Value>,
+    {
+        let mut grads = Gradients::default();
+        let (value, tape) = self.record(seed);
+        self.propagate(value, &tape, Self::Value::UNIT, &mut grads);
+        grads
+    }
+}
+
+/// Plain reverse mode (what `Backward` computes without a seed), implemented for every expression.
+pub trait Gradient: Backward {
+    /// Gradient with respect to every variable at once (see `Gradients::wrt`).
+    #[inline(always)]
+    fn backward(&self) -> Gradients<Self::Value>
+    where
+        Self::Value: Leaf<Unit = &'static Self::Value> + 'static,
+    {
+        let mut grads = Gradients::default();
+        self.backward_from(*Self::Value::UNIT, &mut grads);
+        grads
+    }
+    /// Add `adjoint` times the gradient to `grads`, e.g. to sum weighted gradients of several outputs in one `Gradients`.
+    #[inline(always)]
+    fn backward_from(&self, adjoint: Self::Value, grads: &mut Gradients<Self::Value>) {
+        let (value, tape) = self.record(());
+        self.propagate(value, &tape, adjoint, grads);
+    }
+}
+
+impl<E: Backward> Gradient for E {}
+
+/// Value and tape saved by the forward pass for one child.
+type Saved<T, W> = (<T as Backward<W>>::Value, <T as Backward<W>>::Tape);
+
+/// Seeds that run the reverse pass on `Dual`s (the `forward::Seed`s, as opposed to `()`).
+pub trait DualSeed: Copy {}
+impl<U> DualSeed for &U {}
+impl<const ID: usize> DualSeed for crate::grad::Id<ID> {}
+impl<T, const N: usize> DualSeed for crate::forward::Direction<'_, T, N> {}
+
+// Leaves: plain values, or `Dual`s starting with their tangent along the seed
+
+/// Variables, addressed by `$x`.
+macro_rules! backward_variable {
+    ($($($c:ident: $ct:ty),* => $Name:ty: |$self:ident| $x:expr, $id:expr);+ $(;)?) => {
+        $(
+            impl<T: Leaf + core::ops::Add<Output = T> + Copy, $(const $c: $ct),*> Backward for $Name {
+                type Value = T;
+                type Tape = ();
+                #[inline(always)]
+                fn record(&$self, _seed: ()) -> (T, ()) {
+                    (*$x, ())
+                }
+                #[inline(always)]
+                fn propagate(&$self, _value: T, _tape: &(), adjoint: T, grads: &mut Gradients<T>) {
+                    grads.accumulate($x, adjoint)
+                }
+            }
+            impl<
+                    T: Leaf<Unit = &'static T> + core::ops::Add<Output = T> + Copy + 'static,
+                    W: DualSeed + Seed<T>,
+                    $(const $c: $ct),*
+                > Backward<W> for $Name
+            {
+                type Value = Dual<T>;
+                type Tape = ();
+                #[inline(always)]
+                fn record(&$self, seed: W) -> (Dual<T>, ()) {
+                    let value = *$x;
+                    (Dual { value, deriv: seed.tangent($x, $id) }, ())
+                }
+                #[inline(always)]
+                fn propagate(&$self, _value: Dual<T>, _tape: &(), adjoint: Dual<T>, grads: &mut Gradients<Dual<T>>) {
+                    grads.accumulate($x, adjoint)
+                }
+            }
+        )+
+    };
+}
+
+backward_variable!(
+    => &T: |self| *self, None;
+    ID: usize => Var<ID, &T>: |self| self.0, Some(ID);
+);
+
+/// Constant leaves (`$value` evaluates them): nothing to propagate.
+macro_rules! backward_constant {
+    ($($Name:ident: |$self:ident| $value:expr),+ $(,)?) => {
+        $(
+            impl<T: Leaf<Unit = &'static T> + Copy + 'static> Backward for $Name<T> {
+                type Value = T;
+                type Tape = ();
+                #[inline(always)]
+                fn record(&$self, _seed: ()) -> (T, ()) {
+                    ($value, ())
+                }
+                #[inline(always)]
+                fn propagate(&self, _value: T, _tape: &(), _adjoint: T, _grads: &mut Gradients<T>) {}
+            }
+            impl<T: Leaf<Unit = &'static T> + Copy + 'static, W: DualSeed + Seed<T>> Backward<W> for $Name<T> {
+                type Value = Dual<T>;
+                type Tape = ();
+                #[inline(always)]
+                fn record(&$self, _seed: W) -> (Dual<T>, ()) {
+                    (Dual { value: $value, deriv: *T::ZERO }, ())
+                }
+                #[inline(always)]
+                fn propagate(&self, _value: Dual<T>, _tape: &(), _adjoint: Dual<T>, _grads: &mut Gradients<Dual<T>>) {}
+            }
+        )+
+    };
+}
+
+backward_constant!(
+    Lit: |self| self.0,
+    Delta: |self| *crate::eval::Ref::eval(self),
+    Zero: |self| *crate::eval::Ref::eval(self),
+    One: |self| *crate::eval::Ref::eval(self),
+);
+
+impl<W: Copy, T: Backward<W>> Backward<W> for Expr<T> {
+    type Value = T::Value;
+    type Tape = T::Tape;
+    #[inline(always)]
+    fn record(&self, seed: W) -> (T::Value, T::Tape) {
+        self.0.record(seed)
+    }
+    #[inline(always)]
+    fn propagate(
+        &self,
+        value: T::Value,
+        tape: &T::Tape,
+        adjoint: T::Value,
+        grads: &mut Gradients<T::Value>,
+    ) {
+        self.0.propagate(value, tape, adjoint, grads)
+    }
+}
+
+// Arithmetic
+
+impl<W: Copy, T: Backward<W>> Backward<W> for ops::Neg<T>
+where
+    Self: Eval,
+    T::Value: core::ops::Neg<Output = T::Value>,
+{
+    type Value = T::Value;
+    type Tape = Saved<T, W>;
+    #[inline(always)]
+    fn record(&self, seed: W) -> (T::Value, Saved<T, W>) {
+        let (v, t) = self.0.record(seed);
+        (-v, (v, t))
+    }
+    #[inline(always)]
+    fn propagate(
+        &self,
+        _value: T::Value,
+        tape: &Saved<T, W>,
+        adjoint: T::Value,
+        grads: &mut Gradients<T::Value>,
+    ) {
+        self.0.propagate(tape.0, &tape.1, -adjoint, grads)
+    }
+}
+
+/// Binary arithmetic: `|l, r, value, adjoint| (adjoint of l, adjoint of r)`, with whatever else the rule needs.
+macro_rules! backward_binary {
+    ($Name:ident, $name:ident $(+ $Also:ident)*: |$l:ident, $r:ident, $v:ident, $a:ident| $adjoints:expr) => {
+        impl<W: Copy, L: Backward<W>, R: Backward<W, Value = L::Value>> Backward<W> for ops::$Name<L, R>
+        where
+            Self: Eval,
+            L::Value: core::ops::$Name<Output = L::Value> $(+ core::ops::$Also<Output = L::Value>)*,
+        {
+            type Value = L::Value;
+            type Tape = (Saved<L, W>, Saved<R, W>);
+            #[inline(always)]
+            fn record(&self, seed: W) -> (L::Value, Self::Tape) {
+                let (l, lt) = self.0.record(seed);
+                let (r, rt) = self.1.record(seed);
+                (core::ops::$Name::$name(l, r), ((l, lt), (r, rt)))
+            }
+            #[inline(always)]
+            #[allow(unused_variables)]
+            fn propagate(
+                &self,
+                $v: L::Value,
+                tape: &Self::Tape,
+                $a: L::Value,
+                grads: &mut Gradients<L::Value>,
+            ) {
+                let (($l, ref lt), ($r, ref rt)) = *tape;
+                let (dl, dr) = $adjoints;
+                self.0.propagate($l, lt, dl, grads);
+                self.1.propagate($r, rt, dr, grads);
+            }
+        }
+    };
+}
+
+backward_binary!(Add, add: |l, r, v, a| (a, a));
+backward_binary!(Sub, sub + Neg: |l, r, v, a| (a, -a));
+backward_binary!(Mul, mul: |l, r, v, a| (a * r, a * l));
+backward_binary!(Div, div + Mul + Neg: |l, r, v, a| (a / r, -(a * v / r)));
+backward_binary!(Rem, rem + Sub + Div + Mul + Neg: |l, r, v, a| (a, -(a * ((l - v) / r)))); // see `ops::Rem`
+
+/// Shifts: the shift amount is discrete (see `ops::Shl`), so it only needs to be evaluated, never differentiated.
+macro_rules! backward_shift {
+    ($($Name:ident: $name:ident),+) => {
+        $(
+            impl<W: Copy, L: Backward<W>, R: Eval<Evaluated: Copy>> Backward<W> for ops::$Name<L, R>
+            where
+                Self: Eval,
+                L::Value: core::ops::$Name<R::Evaluated, Output = L::Value>,
+            {
+                type Value = L::Value;
+                type Tape = (Saved<L, W>, R::Evaluated);
+                #[inline(always)]
+                fn record(&self, seed: W) -> (L::Value, Self::Tape) {
+                    let (l, lt) = self.0.record(seed);
+                    let r = (&self.1).eval();
+                    (core::ops::$Name::$name(l, r), ((l, lt), r))
+                }
+                #[inline(always)]
+                fn propagate(
+                    &self,
+                    _value: L::Value,
+                    tape: &Self::Tape,
+                    adjoint: L::Value,
+                    grads: &mut Gradients<L::Value>,
+                ) {
+                    let ((l, ref lt), r) = *tape;
+                    self.0.propagate(l, lt, core::ops::$Name::$name(adjoint, r), grads)
+                }
+            }
+        )+
+    };
+}
+
+backward_shift!(Shl: shl, Shr: shr);
+
+// Functions (compare the chain rules in `ops::Exp` & co.)
+
+/// Unary functions: `|x, value, adjoint| adjoint of x`.
+macro_rules! backward_fn {
+    ($($Name:ident, $name:ident: |$x:ident, $v:ident, $a:ident| $adjoint:expr),+ $(,)?) => {
+        $(
+            impl<W: Copy, T: Backward<W>> Backward<W> for ops::$Name<T>
+            where
+                Self: Eval,
+                T::Value: Float<Value = T::Value>
+                    + core::ops::Neg<Output = T::Value>
+                    + core::ops::Add<Output = T::Value>
+                    + core::ops::Sub<Output = T::Value>
+                    + core::ops::Mul<Output = T::Value>
+                    + core::ops::Div<Output = T::Value>,
+            {
+                type Value = T::Value;
+                type Tape = Saved<T, W>;
+                #[inline(always)]
+                fn record(&self, seed: W) -> (T::Value, Saved<T, W>) {
+                    let (x, t) = self.0.record(seed);
+                    (x.$name(), (x, t))
+                }
+                #[inline(always)]
+                #[allow(unused_variables)]
+                fn propagate(
+                    &self,
+                    $v: T::Value,
+                    tape: &Saved<T, W>,
+                    $a: T::Value,
+                    grads: &mut Gradients<T::Value>,
+                ) {
+                    let ($x, ref t) = *tape;
+                    self.0.propagate($x, t, $adjoint, grads)
+                }
+            }
+        )+
+    };
+}
+
+backward_fn!(
+    Exp, exp: |x, v, a| a * v,
+    Ln, ln: |x, v, a| a / x,
+    Sqrt, sqrt: |x, v, a| a / (v + v),
+    Sin, sin: |x, v, a| a * x.cos(),
+    Cos, cos: |x, v, a| -(a * x.sin()),
+    Tan, tan: |x, v, a| a / (x.cos() * x.cos()),
+    Asin, asin: |x, v, a| a / (T::Value::from_i32(1) - x * x).sqrt(),
+    Acos, acos: |x, v, a| -(a / (T::Value::from_i32(1) - x * x).sqrt()),
+    Atan, atan: |x, v, a| a / (T::Value::from_i32(1) + x * x),
+    Sinh, sinh: |x, v, a| a * x.cosh(),
+    Cosh, cosh: |x, v, a| a * x.sinh(),
+    Tanh, tanh: |x, v, a| a / (x.cosh() * x.cosh()),
+);
+
+impl<W: Copy, T: Backward<W>> Backward<W> for ops::Powi<T>
+where
+    Self: Eval,
+    T::Value: Float<Value = T::Value> + core::ops::Mul<Output = T::Value>,
+{
+    type Value = T::Value;
+    type Tape = Saved<T, W>;
+    #[inline(always)]
+    fn record(&self, seed: W) -> (T::Value, Saved<T, W>) {
+        let (x, t) = self.0.record(seed);
+        (x.powi(self.1), (x, t))
+    }
+    #[inline(always)]
+    fn propagate(
+        &self,
+        _value: T::Value,
+        tape: &Saved<T, W>,
+        adjoint: T::Value,
+        grads: &mut Gradients<T::Value>,
+    ) {
+        let (x, ref t) = *tape;
+        let dx = adjoint * crate::float::powi_slope(x, self.1);
+        self.0.propagate(x, t, dx, grads)
+    }
+}
+
+impl<W: Copy, T: Backward<W>, V: Copy> Backward<W> for ops::Monomial<T, V>
+where
+    Self: Eval,
+    T::Value: Float<Value = T::Value>
+        + From<V>
+        + core::ops::Add<Output = T::Value>
+        + core::ops::Mul<Output = T::Value>,
+{
+    type Value = T::Value;
+    type Tape = Saved<T, W>;
+    #[inline(always)]
+    fn record(&self, seed: W) -> (T::Value, Saved<T, W>) {
+        let (x, t) = self.1.record(seed);
+        (
+            T::Value::from(self.0) * crate::float::powi_wide(x, self.2),
+            (x, t),
+        )
+    }
+    #[inline(always)]
+    fn propagate(
+        &self,
+        _value: T::Value,
+        tape: &Saved<T, W>,
+        adjoint: T::Value,
+        grads: &mut Gradients<T::Value>,
+    ) {
+        let (x, ref t) = *tape;
+        let slope =
+            crate::float::from_i64::<T::Value>(self.2) * crate::float::powi_wide(x, self.2 - 1);
+        self.1
+            .propagate(x, t, adjoint * T::Value::from(self.0) * slope, grads)
+    }
+}
+
+impl<W: Copy, L: Backward<W>, R: Backward<W, Value = L::Value>> Backward<W> for ops::Powf<L, R>
+where
+    Self: Eval,
+    L::Value: Float<Value = L::Value>
+        + Leaf<Unit: Borrow<L::Value>>
+        + core::ops::Sub<Output = L::Value>
+        + core::ops::Mul<Output = L::Value>
+        + PartialEq,
+{
+    type Value = L::Value;
+    type Tape = (Saved<L, W>, Saved<R, W>);
+    #[inline(always)]
+    fn record(&self, seed: W) -> (L::Value, Self::Tape) {
+        let (l, lt) = self.0.record(seed);
+        let (r, rt) = self.1.record(seed);
+        (l.powf(r), ((l, lt), (r, rt)))
+    }
+    #[inline(always)]
+    fn propagate(
+        &self,
+        value: L::Value,
+        tape: &Self::Tape,
+        adjoint: L::Value,
+        grads: &mut Gradients<L::Value>,
+    ) {
+        let ((l, ref lt), (r, ref rt)) = *tape;
+        let dl = adjoint * r * l.powf(r - L::Value::from_i32(1));
+        self.0.propagate(l, lt, dl, grads);
+        // `u^v ln u` is `0 * -inf = NaN` at `u = 0`, but `0^v` is flat in `v` (for `v > 0`, where it's 0)
+        let dr = if value == *L::Value::ZERO.borrow() {
+            *L::Value::ZERO.borrow()
+        } else {
+            adjoint * value * l.ln()
+        };
+        self.1.propagate(r, rt, dr, grads);
+    }
+}
+
+impl<W: Copy, L: Backward<W>, R: Backward<W, Value = L::Value>> Backward<W> for ops::ZeroMul<L, R>
+where
+    Self: Eval,
+    L::Value: Leaf<Unit: Borrow<L::Value>> + core::ops::Mul<Output = L::Value> + PartialEq,
+{
+    type Value = L::Value;
+    type Tape = (Saved<L, W>, Saved<R, W>);
+    #[inline(always)]
+    fn record(&self, seed: W) -> (L::Value, Self::Tape) {
+        let (l, lt) = self.0.record(seed);
+        let (r, rt) = self.1.record(seed);
+        let value = if l == *L::Value::ZERO.borrow() {
+            l
+        } else {
+            l * r
+        };
+        (value, ((l, lt), (r, rt)))
+    }
+    #[inline(always)]
+    fn propagate(
+        &self,
+        _value: L::Value,
+        tape: &Self::Tape,
+        adjoint: L::Value,
+        grads: &mut Gradients<L::Value>,
+    ) {
+        // Only the value is guarded: adjoints follow the product rule, like `grad`
+        let ((l, ref lt), (r, ref rt)) = *tape;
+        self.0.propagate(l, lt, adjoint * r, grads);
+        self.1.propagate(r, rt, adjoint * l, grads);
+    }
+}
+
+impl<W: Copy, L: Backward<W>, R: Backward<W, Value = L::Value>> Backward<W> for ops::Atan2<L, R>
+where
+    Self: Eval,
+    L::Value: Float<Value = L::Value>
+        + core::ops::Neg<Output = L::Value>
+        + core::ops::Add<Output = L::Value>
+        + core::ops::Mul<Output = L::Value>
+        + core::ops::Div<Output = L::Value>,
+{
+    type Value = L::Value;
+    type Tape = (Saved<L, W>, Saved<R, W>);
+    #[inline(always)]
+    fn record(&self, seed: W) -> (L::Value, Self::Tape) {
+        let (y, yt) = self.0.record(seed);
+        let (x, xt) = self.1.record(seed);
+        (y.atan2(x), ((y, yt), (x, xt)))
+    }
+    #[inline(always)]
+    fn propagate(
+        &self,
+        _value: L::Value,
+        tape: &Self::Tape,
+        adjoint: L::Value,
+        grads: &mut Gradients<L::Value>,
+    ) {
+        let ((y, ref yt), (x, ref xt)) = *tape;
+        let scale = adjoint / (x * x + y * y);
+        self.0.propagate(y, yt, scale * x, grads);
+        self.1.propagate(x, xt, -(scale * y), grads);
+    }
+}
@@ -0,0 +1,544 @@
+//! Algebraic simplification: fold identities (`x + 0`, `x * 1`, ...), constant subtrees, and double negations.
+//!
+//! Simplification happens at the type level, so the result is a new, smaller expression type.
+//! Constants known only at runtime (e.g. the `Delta`s that `grad` leaves behind) fold into `Lit`s,
+//! so whole constant subtrees collapse into one value, but they can't decide the shape of the result:
+//! `x * 1` only folds when that `1` is statically `One`.
+//! To fold those too, convert into a `DynExpr` with `IntoDyn::simplify_dyn` (with `alloc`),
+//! which checks every literal against `Leaf::ZERO` and `Leaf::UNIT` at runtime.
+//!
+//! Each rule dispatches on the `Classify::Class` of already-simplified children,
+//! since Rust can't tell a `Zero` from an arbitrary `T` otherwise.
+
+use crate::{
+    eval::Eval,
+    expr::Expr,
+    leaf::{Delta, Leaf, Lit, One, Var, Zero},
+    ops,
+};
+
+/// Classes of simplified nodes, on which simplification rules dispatch.
+pub mod class {
+    /// Statically `Zero`.
+    #[derive(Debug)]
+    pub struct Zero;
+    /// Statically `One`.
+    #[derive(Debug)]
+    pub struct One;
+    /// Constant known at runtime (`Lit`).
+    #[derive(Debug)]
+    pub struct Lit;
+    /// Negation of something else (`Neg`).
+    #[derive(Debug)]
+    pub struct Neg;
+    /// Anything depending on a variable.
+    #[derive(Debug)]
+    pub struct Other;
+}
+
+/// Which class of simplification rules applies to this (simplified) node.
+pub trait Classify {
+    /// One of the types in `simplify::class`.
+    type Class;
+}
+
+/// Fold identities, constant subtrees, and double negations into a new, smaller expression.
+#[const_trait]
+pub trait Simplify: ~const Eval {
+    /// Simplified expression type.
+    type Simplified: ~const Eval + Classify;
+    /// Simplify an expression, which should evaluate to the same value.
+    fn simplify(self) -> Self::Simplified;
+}
+
+/// Negation rules (implemented on classes).
+#[const_trait]
+pub trait Neg<T> {
+    /// Simplified type.
+    type Output: ~const Eval + Classify;
+    /// Simplify `-t`.
+    fn neg(t: T) -> Self::Output;
+}
+
+/// Addition rules (implemented on pairs of classes).
+#[const_trait]
+pub trait Add<L, R> {
+    /// Simplified type.
+    type Output: ~const Eval + Classify;
+    /// Simplify `l + r`.
+    fn add(l: L, r: R) -> Self::Output;
+}
+
+/// Subtraction rules (implemented on pairs of classes).
+#[const_trait]
+pub trait Sub<L, R> {
+    /// Simplified type.
+    type Output: ~const Eval + Classify;
+    /// Simplify `l - r`.
+    fn sub(l: L, r: R) -> Self::Output;
+}
+
+/// Multiplication rules (implemented on pairs of classes).
+#[const_trait]
+pub trait Mul<L, R> {
+    /// Simplified type.
+    type Output: ~const Eval + Classify;
+    /// Simplify `l * r`.
+    fn mul(l: L, r: R) -> Self::Output;
+}
+
+/// Division rules (implemented on pairs of classes).
+#[const_trait]
+pub trait Div<L, R> {
+    /// Simplified type.
+    type Output: ~const Eval + Classify;
+    /// Simplify `l / r`.
+    fn div(l: L, r: R) -> Self::Output;
+}
+
+// Classes
+
+impl<T: Leaf> Classify for Zero<T> {
+    type Class = class::Zero;
+}
+impl<T: Leaf> Classify for One<T> {
+    type Class = class::One;
+}
+impl<T: Leaf> Classify for Lit<T> {
+    type Class = class::Lit;
+}
+impl<T: Eval> Classify for ops::Neg<T> {
+    type Class = class::Neg;
+}
+impl<T: Leaf> Classify for &T {
+    type Class = class::Other;
+}
+impl<T: Leaf, const ID: usize> Classify for Var<ID, &T> {
+    type Class = class::Other;
+}
+impl<T: Eval> Classify for Expr<T> {
+    type Class = class::Other;
+}
+
+// Leaves
+
+macro_rules! simplify_to_self {
+    ($($l:lifetime,)* $($t:ident),+ $(; const $c:ident: $ct:ty)* => $name:ty where { $($bounds:tt)* }) => {
+        impl<$($l,)* $($t,)+ $(const $c: $ct),*> const Simplify for $name where $($bounds)* {
+            type Simplified = Self;
+            #[inline(always)]
+            fn simplify(self) -> Self {
+                self
+            }
+        }
+    };
+}
+
+simplify_to_self!('a, T => &'a T where { T: ~const Leaf });
+simplify_to_self!('a, T; const ID: usize => Var<ID, &'a T> where { T: ~const Leaf });
+simplify_to_self!(T => Lit<T> where { T: ~const Leaf + Copy });
+simplify_to_self!(T => Zero<T> where { T: ~const Leaf });
+simplify_to_self!(T => One<T> where { T: ~const Leaf });
+
+impl<T: ~const Leaf<Unit = &'static T> + Copy + 'static> const Simplify for Delta<T> {
+    type Simplified = Lit<T>;
+    #[inline(always)]
+    fn simplify(self) -> Lit<T> {
+        Lit(*crate::eval::Own::eval(self))
+    }
+}
+
+impl<T: ~const Simplify> const Simplify for Expr<T> {
+    type Simplified = Expr<T::Simplified>;
+    #[inline(always)]
+    fn simplify(self) -> Self::Simplified {
+        Expr(self.0.simplify())
+    }
+}
+
+// Operations
+
+impl<T: ~const Simplify> const Simplify for ops::Neg<T>
+where
+    Self: ~const Eval,
+    <T::Simplified as Classify>::Class: ~const Neg<T::Simplified>,
+{
+    type Simplified = <<T::Simplified as Classify>::Class as Neg<T::Simplified>>::Output;
+    #[inline(always)]
+    fn simplify(self) -> Self::Simplified {
+        <<T::Simplified as Classify>::Class as Neg<T::Simplified>>::neg(self.0.simplify())
+    }
+}
+
+macro_rules! simplify_binary {
+    ($($Name:ident, $name:ident;)+) => {
+        $(
+            impl<L: ~const Simplify, R: ~const Simplify> const Simplify for ops::$Name<L, R>
+            where
+                Self: ~const Eval,
+                (<L::Simplified as Classify>::Class, <R::Simplified as Classify>::Class):
+                    ~const $Name<L::Simplified, R::Simplified>,
+            {
+                type Simplified = <(
+                    <L::Simplified as Classify>::Class,
+                    <R::Simplified as Classify>::Class,
+                ) as $Name<L::Simplified, R::Simplified>>::Output;
+                #[inline(always)]
+                fn simplify(self) -> Self::Simplified {
+                    <(
+                        <L::Simplified as Classify>::Class,
+                        <R::Simplified as Classify>::Class,
+                    ) as $Name<L::Simplified, R::Simplified>>::$name(self.0.simplify(), self.1.simplify())
+                }
+            }
+        )+
+    };
+}
+
+simplify_binary!(Add, add; Sub, sub; Mul, mul; Div, div;);
+
+/// Operations without any rules of their own: simplify their arguments and leave them be.
+macro_rules! simplify_rebuild {
+    (unary: $($Unary:ident),*; binary: $($Binary:ident),*) => {
+        $(
+            impl<T: Eval> Classify for ops::$Unary<T> {
+                type Class = class::Other;
+            }
+            impl<T: ~const Simplify> const Simplify for ops::$Unary<T>
+            where
+                Self: ~const Eval,
+                ops::$Unary<T::Simplified>: ~const Eval,
+            {
+                type Simplified = ops::$Unary<T::Simplified>;
+                #[inline(always)]
+                fn simplify(self) -> Self::Simplified {
+                    ops::$Unary(self.0.simplify())
+                }
+            }
+        )*
+        $(
+            impl<L: Eval, R: Eval> Classify for ops::$Binary<L, R> {
+                type Class = class::Other;
+            }
+            impl<L: ~const Simplify, R: ~const Simplify> const Simplify for ops::$Binary<L, R>
+            where
+                Self: ~const Eval,
+                ops::$Binary<L::Simplified, R::Simplified>: ~const Eval,
+            {
+                type Simplified = ops::$Binary<L::Simplified, R::Simplified>;
+                #[inline(always)]
+                fn simplify(self) -> Self::Simplified {
+                    ops::$Binary(self.0.simplify(), self.1.simplify())
+                }
+            }
+        )*
+    };
+}
+
+simplify_rebuild!(
+    unary: Not, Exp, Ln, Sqrt, Sin, Cos, Tan, Asin, Acos, Atan, Sinh, Cosh, Tanh;
+    binary: BitAnd, BitOr, BitXor, Rem, Shl, Shr, Powf, Atan2, ZeroMul
+);
+
+impl<T: Eval> Classify for ops::Powi<T> {
+    type Class = class::Other;
+}
+impl<T: ~const Simplify> const Simplify for ops::Powi<T>
+where
+    Self: ~const Eval,
+    ops::Powi<T::Simplified>: ~const Eval,
+{
+    type Simplified = ops::Powi<T::Simplified>;
+    #[inline(always)]
+    fn simplify(self) -> Self::Simplified {
+        ops::Powi(self.0.simplify(), self.1)
+    }
+}
+
+impl<T: Eval, V> Classify for ops::Monomial<T, V> {
+    type Class = class::Other;
+}
+impl<T: ~const Simplify, V> const Simplify for ops::Monomial<T, V>
+where
+    Self: ~const Eval,
+    ops::Monomial<T::Simplified, V>: ~const Eval,
+{
+    type Simplified = ops::Monomial<T::Simplified, V>;
+    #[inline(always)]
+    fn simplify(self) -> Self::Simplified {
+        ops::Monomial(self.0, self.1.simplify(), self.2)
+    }
+}
+
+macro_rules! classify_other {
+    ($($Name:ident),+) => {
+        $(
+            impl<L: Eval, R: Eval> Classify for ops::$Name<L, R> {
+                type Class = class::Other;
+            }
+        )+
+    };
+}
+
+classify_other!(Add, Sub, Mul, Div);
+
+// Rules
+
+/// `-t` exactly as written.
+macro_rules! neg_keep {
+    ($($C:ident),+) => {
+        $(
+            impl<T: ~const Eval<Evaluated: ~const core::ops::Neg>> const Neg<T> for class::$C {
+                type Output = ops::Neg<T>;
+                #[inline(always)]
+                fn neg(t: T) -> Self::Output {
+                    ops::Neg(t)
+                }
+            }
+        )+
+    };
+}
+
+/// `-t` evaluated right away into a `Lit`.
+macro_rules! neg_fold {
+    ($($C:ident),+) => {
+        $(
+            impl<T: ~const Eval<Evaluated: ~const core::ops::Neg<Output: ~const Leaf + Copy>>> const Neg<T>
+                for class::$C
+            {
+                type Output = Lit<<T::Evaluated as core::ops::Neg>::Output>;
+                #[inline(always)]
+                fn neg(t: T) -> Self::Output {
+                    Lit(-t.eval())
+                }
+            }
+        )+
+    };
+}
+
+impl<T: ~const Eval + Classify> const Neg<T> for class::Zero {
+    type Output = T;
+    #[inline(always)]
+    fn neg(t: T) -> T {
+        t
+    }
+}
+neg_fold!(One, Lit);
+impl<T: ~const Eval> const Neg<ops::Neg<T>> for class::Neg
+where
+    ops::Neg<T>: ~const Eval,
+    T: Classify,
+{
+    type Output = T;
+    #[inline(always)]
+    fn neg(t: ops::Neg<T>) -> T {
+        t.0
+    }
+}
+neg_keep!(Other);
+
+/// `l op r` exactly as written.
+macro_rules! keep {
+    ($Name:ident, $name:ident: $(($LC:ident, $RC:ident)),+) => {
+        $(
+            impl<L: ~const Eval<Evaluated: ~const core::ops::$Name<R::Evaluated>>, R: ~const Eval> const
+                $Name<L, R> for (class::$LC, class::$RC)
+            {
+                type Output = ops::$Name<L, R>;
+                #[inline(always)]
+                fn $name(l: L, r: R) -> Self::Output {
+                    ops::$Name(l, r)
+                }
+            }
+        )+
+    };
+}
+
+/// `l op r` evaluated right away into a `Lit`.
+macro_rules! fold {
+    ($Name:ident, $name:ident: $(($LC:ident, $RC:ident)),+) => {
+        $(
+            impl<
+                    L: ~const Eval<
+                        Evaluated: ~const core::ops::$Name<R::Evaluated, Output: ~const Leaf + Copy>,
+                    >,
+                    R: ~const Eval,
+                > const $Name<L, R> for (class::$LC, class::$RC)
+            {
+                type Output = Lit<<L::Evaluated as core::ops::$Name<R::Evaluated>>::Output>;
+                #[inline(always)]
+                fn $name(l: L, r: R) -> Self::Output {
+                    Lit(core::ops::$Name::$name(l.eval(), r.eval()))
+                }
+            }
+        )+
+    };
+}
+
+/// `l op r` is just `l`.
+macro_rules! left {
+    ($Name:ident, $name:ident: $(($LC:ident, $RC:ident)),+) => {
+        $(
+            impl<L: ~const Eval + Classify, R> const $Name<L, R> for (class::$LC, class::$RC) {
+                type Output = L;
+                #[inline(always)]
+                fn $name(l: L, r: R) -> L {
+                    core::mem::forget(r);
+                    l
+                }
+            }
+        )+
+    };
+}
+
+/// `l op r` is just `r`.
+macro_rules! right {
+    ($Name:ident, $name:ident: $(($LC:ident, $RC:ident)),+) => {
+        $(
+            impl<L, R: ~const Eval + Classify> const $Name<L, R> for (class::$LC, class::$RC) {
+                type Output = R;
+                #[inline(always)]
+                fn $name(l: L, r: R) -> R {
+                    core::mem::forget(l);
+                    r
+                }
+            }
+        )+
+    };
+}
+
+right!(
+    Add,
+    add: (Zero, Zero),
+    (Zero, One),
+    (Zero, Lit),
+    (Zero, Neg),
+    (Zero, Other)
+);
+left!(
+    Add,
+    add: (One, Zero),
+    (Lit, Zero),
+    (Neg, Zero),
+    (Other, Zero)
+);
+fold!(Add, add: (One, One), (One, Lit), (Lit, One), (Lit, Lit));
+keep!(Add, add: (One, Neg), (One, Other), (Lit, Neg), (Lit, Other));
+keep!(Add, add: (Neg, One), (Neg, Lit), (Neg, Neg), (Neg, Other));
+keep!(
+    Add,
+    add: (Other, One),
+    (Other, Lit),
+    (Other, Neg),
+    (Other, Other)
+);
+
+left!(
+    Sub,
+    sub: (Zero, Zero),
+    (One, Zero),
+    (Lit, Zero),
+    (Neg, Zero),
+    (Other, Zero)
+);
+fold!(Sub, sub: (One, One), (One, Lit), (Lit, One), (Lit, Lit));
+keep!(Sub, sub: (One, Other), (Lit, Other));
+keep!(Sub, sub: (Neg, One), (Neg, Lit), (Neg, Other));
+keep!(Sub, sub: (Other, One), (Other, Lit), (Other, Other));
+
+/// `0 - r` is `-r`.
+macro_rules! sub_from_zero {
+    ($($RC:ident),+) => {
+        $(
+            impl<L, R> const Sub<L, R> for (class::Zero, class::$RC)
+            where
+                class::$RC: ~const Neg<R>,
+            {
+                type Output = <class::$RC as Neg<R>>::Output;
+                #[inline(always)]
+                fn sub(l: L, r: R) -> Self::Output {
+                    core::mem::forget(l);
+                    <class::$RC as Neg<R>>::neg(r)
+                }
+            }
+        )+
+    };
+}
+
+sub_from_zero!(One, Lit, Neg, Other);
+
+/// `l - -r` is `l + r`.
+macro_rules! sub_neg {
+    ($($LC:ident),+) => {
+        $(
+            impl<L: ~const Eval<Evaluated: ~const core::ops::Add<R::Evaluated>>, R: ~const Eval> const
+                Sub<L, ops::Neg<R>> for (class::$LC, class::Neg)
+            where
+                ops::Neg<R>: ~const Eval,
+            {
+                type Output = ops::Add<L, R>;
+                #[inline(always)]
+                fn sub(l: L, r: ops::Neg<R>) -> Self::Output {
+                    ops::Add(l, r.0)
+                }
+            }
+        )+
+    };
+}
+
+sub_neg!(One, Lit, Neg, Other);
+
+// Only a statically `Zero` or `One` zeroes out the product: `0 * x` is `NaN` if `x` is, or if it's infinite.
+left!(Mul, mul: (Zero, Zero), (Zero, One));
+right!(Mul, mul: (One, Zero));
+fold!(Mul, mul: (Zero, Lit), (Lit, Zero));
+keep!(
+    Mul,
+    mul: (Zero, Neg),
+    (Zero, Other),
+    (Neg, Zero),
+    (Other, Zero)
+);
+right!(Mul, mul: (One, One), (One, Lit), (One, Neg), (One, Other));
+left!(Mul, mul: (Lit, One), (Neg, One), (Other, One));
+fold!(Mul, mul: (Lit, Lit));
+keep!(Mul, mul: (Lit, Neg), (Lit, Other), (Neg, Lit), (Neg, Other));
+keep!(Mul, mul: (Other, Lit), (Other, Neg), (Other, Other));
+
+/// `-l * -r` is `l * r`, and likewise for division.
+macro_rules! neg_neg {
+    ($Name:ident, $name:ident) => {
+        impl<L: ~const Eval<Evaluated: ~const core::ops::$Name<R::Evaluated>>, R: ~const Eval> const
+            $Name<ops::Neg<L>, ops::Neg<R>> for (class::Neg, class::Neg)
+        where
+            ops::Neg<L>: ~const Eval,
+            ops::Neg<R>: ~const Eval,
+        {
+            type Output = ops::$Name<L, R>;
+            #[inline(always)]
+            fn $name(l: ops::Neg<L>, r: ops::Neg<R>) -> Self::Output {
+                ops::$Name(l.0, r.0)
+            }
+        }
+    };
+}
+
+neg_neg!(Mul, mul);
+neg_neg!(Div, div);
+
+left!(Div, div: (Zero, One));
+fold!(Div, div: (Zero, Lit)); // zero unless the literal is, in which case still `0 / 0`
+keep!(Div, div: (Zero, Neg), (Zero, Other)); // don't hide division by zero
+left!(Div, div: (One, One), (Lit, One), (Neg, One), (Other, One));
+fold!(Div, div: (One, Lit), (Lit, Lit));
+keep!(
+    Div,
+    div: (Zero, Zero),
+    (One, Zero),
+    (Lit, Zero),
+    (Neg, Zero),
+    (Other, Zero)
+); // don't hide division by zero
+keep!(Div, div: (One, Neg), (One, Other), (Lit, Neg), (Lit, Other));
+keep!(Div, div: (Neg, Lit), (Neg, Other));
+keep!(Div, div: (Other, Lit), (Other, Neg), (Other, Other));
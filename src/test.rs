@@ -1,4 +1,18 @@
-use crate::{expr::Expr, ops, prelude::*};
+#![allow(clippy::bool_assert_comparison, clippy::double_neg)]
+
+#[cfg(feature = "id-folding")]
+use crate::leaf::{One, Var};
+use crate::{
+    expr::{one, zero, Expr},
+    forward::Dual,
+    grad::{hessian, Id},
+    jacobian::*,
+    leaf::{Delta, Lit, Zero},
+    ops,
+    prelude::*,
+};
+#[cfg(feature = "std")]
+use crate::{forward::direction, grad::hvp};
 
 #[cfg(feature = "std")]
 #[test]
@@ -112,24 +126,87 @@ fn grad_tautological_other_runtime() {
     assert_eq!(0, *v);
 }
 
-// TODO: investigate the below!
+#[test]
+fn grad_tautological_self_comptime() {
+    const X: i32 = 1;
+    #[cfg(feature = "id-folding")]
+    const G: Expr<One<i32>> = var_id::<0, _>(&X).grad_id::<0>();
+    #[cfg(not(feature = "id-folding"))]
+    const G: Expr<Delta<i32>> = var_id::<0, _>(&X).grad_id::<0>();
+    const V: &i32 = G.eval();
+    assert_eq!(1, *V);
+}
 
-// #[test]
-// fn grad_tautological_self_comptime() {
-//     const X: i32 = 1;
-//     const G: Expr<&i32> = var(&X).grad(&X);
-//     const V: &i32 = G.eval();
-//     assert_eq!(1, *V);
-// }
+#[test]
+fn grad_tautological_other_comptime() {
+    const X: i32 = 1;
+    #[cfg(feature = "id-folding")]
+    const G: Expr<Zero<i32>> = var_id::<0, _>(&X).grad_id::<1>();
+    #[cfg(not(feature = "id-folding"))]
+    const G: Expr<Delta<i32>> = var_id::<0, _>(&X).grad_id::<1>();
+    const V: &i32 = G.eval();
+    assert_eq!(0, *V);
+}
 
-// #[test]
-// fn grad_tautological_other_comptime() {
-//     const X: i32 = 1;
-//     const Y: i32 = 1; // same value!
-//     const G: Expr<&i32> = var(&X).grad(&Y);
-//     const V: &i32 = G.eval();
-//     assert_eq!(0, *V);
-// }
+#[cfg(feature = "id-folding")]
+#[test]
+fn grad_id_add_folds_by_type() {
+    const A: i32 = 37;
+    const B: i32 = 42;
+    const GA: Expr<One<i32>> = (var_id::<0, _>(&A) + var_id::<1, _>(&B)).grad_id::<0>();
+    const GB: Expr<One<i32>> = (var_id::<0, _>(&A) + var_id::<1, _>(&B)).grad_id::<1>();
+    const GC: Expr<Zero<i32>> = (var_id::<0, _>(&A) + var_id::<1, _>(&B)).grad_id::<2>();
+    assert_eq!((&1, &1, &0), (GA.eval(), GB.eval(), GC.eval()));
+}
+
+#[cfg(feature = "id-folding")]
+#[test]
+fn grad_id_mul_comptime() {
+    const A: i32 = 37;
+    const B: i32 = 42;
+    // Each partial folds down to the other factor
+    const GA: Expr<Var<1, &i32>> = (var_id::<0, _>(&A) * var_id::<1, _>(&B)).grad_id::<0>();
+    const GB: Expr<Var<0, &i32>> = (var_id::<0, _>(&A) * var_id::<1, _>(&B)).grad_id::<1>();
+    assert_eq!(42, *GA.eval());
+    assert_eq!(37, *GB.eval());
+}
+
+#[cfg(not(feature = "id-folding"))]
+#[test]
+fn grad_id_delta_comptime() {
+    const A: i32 = 37;
+    const B: i32 = 42;
+    const GA: i32 = (var_id::<0, _>(&A) * var_id::<1, _>(&B))
+        .grad_id::<0>()
+        .eval();
+    const GB: i32 = (var_id::<0, _>(&A) * var_id::<1, _>(&B))
+        .grad_id::<1>()
+        .eval();
+    const GC: i32 = (var_id::<0, _>(&A) + var_id::<1, _>(&B))
+        .grad_id::<2>()
+        .eval();
+    assert_eq!((42, 37, 0), (GA, GB, GC));
+}
+
+#[test]
+fn grad_var_id_by_reference_runtime() {
+    let a = 4;
+    let b = 4;
+    let expr = var_id::<0, _>(&a) * var(&b);
+    assert_eq!(4, (&expr).grad(&a).eval());
+    #[cfg(feature = "id-folding")]
+    {
+        assert_eq!(4, *(&expr).grad_id::<0>().eval());
+        let g: Expr<Zero<i32>> = (&expr).grad_id::<1>();
+        assert_eq!(0, *g.eval());
+    }
+    #[cfg(not(feature = "id-folding"))]
+    {
+        assert_eq!(4, (&expr).grad_id::<0>().eval());
+        assert_eq!(0, (&expr).grad_id::<1>().eval());
+    }
+    assert_eq!(4, expr.grad(&b).eval());
+}
 
 #[test]
 fn grad_add_runtime() {
@@ -151,20 +228,1510 @@ fn grad_sub_runtime() {
     assert_eq!(-1, gb.eval());
 }
 
-// #[test]
-// fn grad_mul_runtime() {
-//     let a = 37;
-//     let b = 42;
-//     let expr = var(&a) * var(&b);
-//     assert_eq!(42, (&expr).grad(&a).eval());
-//     assert_eq!(37, expr.grad(&b).eval());
-// }
-
-// TODO:
-// #[test]
-// fn grad_mul_by_constant() {
-//     (var(&2) * var(&1)).eval();
-//     (2 * var(&1)).eval();
-//     let x = 4;
-//     (var(&2) * var(&x)).grad(&x);
-// }
+#[test]
+fn grad_mul_runtime() {
+    let a = 37;
+    let b = 42;
+    let expr = var(&a) * var(&b);
+    assert_eq!(42, (&expr).grad(&a).eval());
+    assert_eq!(37, expr.grad(&b).eval());
+}
+
+#[test]
+fn const_mul_var() {
+    const VALUE: i32 = (var(&37) * var(&42)).eval();
+    assert_eq!(1554, VALUE);
+}
+
+#[test]
+fn grad_mul_nested_runtime() {
+    let a = 3;
+    let b = 5;
+    let c = 7;
+    let expr = (var(&a) * var(&b)) * (var(&a) * var(&c)); // a^2 bc
+    assert_eq!(2 * 3 * 5 * 7, (&expr).grad(&a).eval());
+    assert_eq!(3 * 3 * 7, (&expr).grad(&b).eval());
+    assert_eq!(3 * 3 * 5, expr.grad(&c).eval());
+}
+
+#[test]
+fn grad_mul_repeated_runtime() {
+    let x: i32 = 3;
+    let expr = var(&x) * var(&x) * var(&x); // x^3
+    let d1 = (&expr).grad(&x);
+    let d2 = (&d1).grad(&x);
+    let d3 = (&d2).grad(&x);
+    assert_eq!(27, expr.eval());
+    assert_eq!(27, d1.eval());
+    assert_eq!(18, d2.eval());
+    assert_eq!(6, d3.eval());
+    assert_eq!(0, *d3.grad(&x).eval());
+}
+
+#[test]
+fn grad_div_runtime() {
+    let a = 3.;
+    let b = 4.;
+    let expr = var(&a) / var(&b);
+    assert_eq!(0.25, (&expr).grad(&a).eval());
+    assert_eq!(-0.1875, expr.grad(&b).eval());
+}
+
+#[test]
+fn grad_div_rational_runtime() {
+    let x = 2.;
+    let expr = (var(&x) * var(&x)) / (var(&x) + var(&x) * var(&x)); // x^2 / (x + x^2)
+    let owned = (var(&x) * var(&x)) / (var(&x) + var(&x) * var(&x));
+    // d/dx = 1 / (1 + x)^2
+    assert_eq!(1. / 9., (&expr).grad(&x).eval());
+    assert_eq!(1. / 9., owned.grad(&x).eval());
+    // d^2/dx^2 = -2 / (1 + x)^3
+    let error: f64 = expr.grad(&x).grad(&x).eval() + 2. / 27.;
+    assert!(-1e-12 < error && error < 1e-12);
+}
+
+#[test]
+fn grad_rem_runtime() {
+    let a: i32 = 17;
+    let b: i32 = 5;
+    let expr = var(&a) % var(&b);
+    assert_eq!(2, (&expr).eval());
+    assert_eq!(1, (&expr).grad(&a).eval());
+    assert_eq!(-3, expr.grad(&b).eval());
+    let expr = var(&-17) % var(&b); // truncated toward zero like `%` itself
+    assert_eq!(3, expr.grad(&b).eval());
+}
+
+#[test]
+fn grad_rem_float_runtime() {
+    let angle: f64 = 7.5;
+    let period: f64 = 2.;
+    let wrapped = var(&angle) % var(&period);
+    assert_eq!(1.5, (&wrapped).eval());
+    assert_eq!(1., (&wrapped).grad(&angle).eval());
+    assert_eq!(-3., wrapped.grad(&period).eval());
+    assert_eq!(
+        2.,
+        (var(&angle) * var(&angle) % 3.).grad(&angle).eval() / angle
+    );
+}
+
+#[test]
+fn const_grad_rem() {
+    const A: i32 = 17;
+    const DVDA: &i32 = (var_id::<0, _>(&A) % 5).grad_id::<0>().eval(); // statically just `a'`
+    const DVDB: i32 = (A % var_id::<0, _>(&5)).grad_id::<0>().eval();
+    assert_eq!(&1, DVDA);
+    assert_eq!(-3, DVDB);
+}
+
+#[test]
+fn grad_shift_runtime() {
+    let x: i32 = 5;
+    let n: u32 = 4;
+    let expr = (3 * var(&x)) << var(&n); // fixed-point scaling: 3x * 2^n
+    assert_eq!(240, (&expr).eval());
+    assert_eq!(48, (&expr).grad(&x).eval());
+    assert_eq!(0, expr.grad(&n).eval()); // discrete shift amount
+    let expr = (var(&x) * var(&x)) >> 2_u32;
+    assert_eq!(2, (&expr).grad(&x).eval()); // 2x / 4, rounded down
+    let _: Expr<Zero<u32>> = (lit(1_u32) << var(&n)).grad(&n);
+}
+
+#[test]
+fn const_grad_shift() {
+    const X: i32 = 5;
+    const DVDX: i32 = ((var_id::<0, _>(&X) * var_id::<0, _>(&X)) << 3_u32)
+        .grad_id::<0>()
+        .eval();
+    assert_eq!(80, DVDX);
+}
+
+#[test]
+fn grad_bool_leaves() {
+    let a = true;
+    let b = false;
+    assert_eq!(&true, (!var(&a)).grad(&a).eval());
+    assert_eq!(&false, (!var(&a)).grad(&b).eval());
+    assert_eq!(false, (var(&a) & var(&b)).grad(&a).eval()); // b is false: a doesn't matter
+    assert_eq!(true, (var(&a) & var(&b)).grad(&b).eval());
+    assert_eq!(false, (var(&a) | var(&b)).grad(&b).eval()); // a is true: b doesn't matter
+    assert_eq!(true, (var(&a) | var(&b)).grad(&a).eval());
+    assert_eq!(true, (var(&a) ^ var(&b)).grad(&b).eval());
+    assert_eq!(false, (var(&a) ^ var(&a)).grad(&a).eval());
+}
+
+#[test]
+fn grad_bool_matches_boolean_difference() {
+    // Multiplexer: s ? a : b
+    fn mux(s: bool, a: bool, b: bool) -> bool {
+        (var(&s) & var(&a) | !var(&s) & var(&b)).eval()
+    }
+    for i in 0..8 {
+        let (s, a, b) = (i & 1 != 0, i & 2 != 0, i & 4 != 0);
+        let expr = var(&s) & var(&a) | !var(&s) & var(&b);
+        assert_eq!(mux(false, a, b) ^ mux(true, a, b), (&expr).grad(&s).eval());
+        assert_eq!(mux(s, false, b) ^ mux(s, true, b), (&expr).grad(&a).eval());
+        assert_eq!(mux(s, a, false) ^ mux(s, a, true), expr.grad(&b).eval());
+    }
+}
+
+#[test]
+fn const_grad_bool() {
+    const A: bool = true;
+    const B: bool = true;
+    const DVDA: bool = (var_id::<0, _>(&A) & var_id::<1, _>(&B) | false)
+        .grad_id::<0>()
+        .eval();
+    assert_eq!(true, DVDA);
+}
+
+#[test]
+fn grad_mul_by_constant() {
+    assert_eq!(2, (var(&2) * var(&1)).eval());
+    assert_eq!(2, (2 * var(&1_i32)).eval());
+    let x: i32 = 4;
+    assert_eq!(2, (var(&2) * var(&x)).grad(&x).eval());
+    assert_eq!(2, (2 * var(&x)).grad(&x).eval());
+    assert_eq!(3, (var(&x) * 3).grad(&x).eval());
+}
+
+#[test]
+fn const_lit() {
+    const VALUE: f64 = (lit(2.) * var(&4_f64) + 1.).eval();
+    assert_eq!(9., VALUE);
+}
+
+#[test]
+fn grad_lit_runtime() {
+    let x: f64 = 3.;
+    let g = lit(2.).grad(&x);
+    let v: &f64 = g.eval();
+    assert_eq!(0., *v);
+    let expr = 1. / var(&x) - 0.5;
+    assert_eq!(-1. / 9., (&expr).grad(&x).eval());
+    assert_eq!(1. / 3. - 0.5, expr.eval());
+}
+
+#[test]
+fn grad_lit_collapses() {
+    let x: i32 = 4;
+    let g: Expr<ops::Mul<Lit<i32>, Delta<i32>>> = (2 * var(&x)).grad(&x);
+    assert_eq!(2, g.eval());
+    let g: Expr<Delta<i32>> = (var(&x) + lit(1)).grad(&x);
+    assert_eq!(1, *g.eval());
+    let g: Expr<Zero<i32>> = (lit(2) * lit(3) - lit(1)).grad(&x);
+    assert_eq!(0, *g.eval());
+}
+
+#[test]
+fn grad_one_zero_collapse() {
+    let x: i32 = 4;
+    let g: Expr<Delta<i32>> = (one::<i32>() * var(&x)).grad(&x);
+    assert_eq!(1, *g.eval());
+    let g: Expr<Zero<i32>> = (zero::<i32>() * var(&x)).grad(&x).grad(&x);
+    assert_eq!(0, *g.eval());
+    let g: Expr<ops::Neg<Delta<i32>>> = (zero::<i32>() - var(&x)).grad(&x);
+    assert_eq!(-1, g.eval());
+    assert_eq!(4, (var(&x) / one::<i32>()).eval());
+}
+
+#[test]
+fn grad_repeated_collapses() {
+    let x = 3;
+    let g: Expr<Zero<i32>> = (lit(5) * var(&x))
+        .grad(&x)
+        .grad(&x)
+        .grad(&x)
+        .grad(&x);
+    assert_eq!(0, *g.eval());
+}
+
+#[cfg(feature = "id-folding")]
+#[test]
+fn const_grad_lit_collapses() {
+    const X: i32 = 3;
+    const G: Expr<Lit<i32>> = (lit(2) * var_id::<0, _>(&X)).grad_id::<0>();
+    const V: i32 = G.eval();
+    assert_eq!(2, V);
+}
+
+#[test]
+fn simplify_double_neg() {
+    let x = 4;
+    let s: Expr<&i32> = (--var(&x)).simplify();
+    assert_eq!(4, *s.eval());
+    let s: Expr<ops::Neg<&i32>> = (---var(&x)).simplify();
+    assert_eq!(-4, s.eval());
+}
+
+#[test]
+fn const_simplify_double_neg() {
+    const X: i32 = 4;
+    const S: Expr<&i32> = (--var(&X)).simplify();
+    assert_eq!(4, *S.eval());
+}
+
+#[test]
+fn simplify_identities() {
+    let x = 4;
+    let y = 5;
+    let s: Expr<&i32> = (var(&x) + zero::<i32>()).simplify();
+    assert_eq!(4, *s.eval());
+    let s: Expr<&i32> = (one::<i32>() * var(&x)).simplify();
+    assert_eq!(4, *s.eval());
+    let s: Expr<Zero<i32>> = (one::<i32>() * zero::<i32>()).simplify();
+    assert_eq!(0, *s.eval());
+    let s: Expr<ops::Neg<&i32>> = (zero::<i32>() - var(&x)).simplify();
+    assert_eq!(-4, s.eval());
+    let s: Expr<ops::Add<&i32, &i32>> = (var(&x) - -var(&y)).simplify();
+    assert_eq!(9, s.eval());
+    let s: Expr<ops::Mul<&i32, &i32>> = ((-var(&x)) * (-var(&y))).simplify();
+    assert_eq!(20, s.eval());
+}
+
+#[test]
+fn simplify_keeps_nan_and_division_by_zero() {
+    let x = f64::NAN;
+    let y = 0.0;
+    let s: Expr<ops::Mul<&f64, Zero<f64>>> = (var(&x) * zero::<f64>()).simplify();
+    assert!(s.eval().is_nan());
+    let s: Expr<ops::Div<Zero<f64>, &f64>> = (zero::<f64>() / var(&y)).simplify();
+    assert!(s.eval().is_nan());
+    let s: Expr<Lit<f64>> = (zero::<f64>() / lit(0.0)).simplify();
+    assert!(s.eval().is_nan());
+    let s: Expr<Lit<f64>> = (zero::<f64>() / lit(2.0)).simplify();
+    assert_eq!(0.0, s.eval());
+}
+
+#[test]
+fn simplify_constant_subtrees() {
+    let x = 4;
+    let s: Expr<ops::Add<Lit<i32>, &i32>> = (lit(2) * lit(3) - lit(1) + var(&x)).simplify();
+    assert_eq!(9, s.eval());
+    let s: Expr<Lit<i32>> = (lit(2) * (lit(3) + one::<i32>())).simplify();
+    assert_eq!(8, s.eval());
+    let s: Expr<Lit<i32>> = (-lit(2)).simplify();
+    assert_eq!(-2, s.eval());
+}
+
+#[test]
+fn simplify_grad() {
+    let x: i32 = 3;
+    let y: i32 = 5;
+    let expr = var(&x) * var(&y) + lit(2) * var(&x);
+    let g = (&expr).grad(&x);
+    type ProductRule<'a> = ops::Add<ops::Mul<Lit<i32>, &'a i32>, ops::Mul<&'a i32, Lit<i32>>>;
+    let s: Expr<ops::Add<ProductRule, Lit<i32>>> = g.simplify();
+    assert_eq!(g.eval(), s.eval());
+    let g = expr.grad(&x).grad(&x);
+    let s: Expr<Lit<i32>> = g.simplify();
+    assert_eq!(g.eval(), s.eval());
+}
+
+#[cfg(feature = "std")]
+fn assert_close(expected: f64, actual: f64) {
+    assert!((expected - actual).abs() < 1e-12, "{expected} != {actual}");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn grad_exp_ln_sqrt_runtime() {
+    let x: f64 = 1.5;
+    assert_close(x.exp(), var(&x).exp().eval());
+    assert_close(x.exp(), var(&x).exp().grad(&x).eval());
+    assert_close(1. / x, var(&x).ln().grad(&x).eval());
+    assert_close(0.5 / x.sqrt(), var(&x).sqrt().grad(&x).eval());
+    assert_close(
+        -0.25 / (x * x.sqrt()),
+        var(&x).sqrt().grad(&x).grad(&x).eval(),
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn grad_exp_chain_rule() {
+    let x: f64 = 0.75;
+    let y: f64 = 2.;
+    let expr = (var(&x) * var(&y)).exp(); // e^xy
+    assert_close(y * (x * y).exp(), (&expr).grad(&x).eval());
+    assert_close(x * (x * y).exp(), expr.grad(&y).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn grad_pow_runtime() {
+    let x: f64 = 1.5;
+    let y: f64 = 2.5;
+    assert_close(x.powi(3), var(&x).powi(3).eval());
+    assert_close(3. * x * x, var(&x).powi(3).grad(&x).eval());
+    assert_close(6. * x, var(&x).powi(3).grad(&x).grad(&x).eval());
+    assert_close(2.5 * x.powf(1.5), var(&x).powf(lit(2.5)).grad(&x).eval());
+    let expr = var(&x).powf(var(&y));
+    assert_close(y * x.powf(y - 1.), (&expr).grad(&x).eval());
+    assert_close(x.powf(y) * x.ln(), expr.grad(&y).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn grad_pow_edge_cases() {
+    let zero: f64 = 0.;
+    assert_eq!(0., var(&zero).powf(lit(2.)).grad(&zero).eval()); // not `NaN` from `ln 0`
+    assert_eq!(
+        var(&zero).powf(lit(2.)).eval_dual(&zero).deriv,
+        var(&zero).powf(lit(2.)).grad(&zero).eval()
+    );
+    let two: f64 = 2.;
+    assert_eq!(0., var(&zero).powf(var(&two)).grad(&two).eval()); // `0^v` is flat, not `0 * ln 0`
+                                                                  // `n - 1` doesn't fit an `i32` for `n = i32::MIN`, but the derivative is still `n u^(n - 1)`
+    let n = i32::MIN as f64;
+    for u in [1., 2., 0.5, 0., -0.5] {
+        let expected = n * f64::powf(u, n - 1.); // -0, -inf, or (at u = ±1) exactly `n`
+        assert_eq!(expected, var(&u).powi(i32::MIN).grad(&u).eval());
+        assert_eq!(expected, var(&u).powi(i32::MIN).eval_dual(&u).deriv);
+    }
+    let one: f64 = 1.;
+    assert_eq!(
+        n * (n - 1.),
+        var(&one).powi(i32::MIN).grad(&one).grad(&one).eval()
+    );
+    assert_eq!("-2147483648*pow(x, -2147483648)*pow(x, -1)*1", {
+        let g = var(&one).powi(i32::MIN).grad(&one);
+        g.named(&[(&one, "x")]).to_string()
+    });
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn grad_f32_transcendental() {
+    let x: f32 = 4.;
+    assert_eq!(0.25, var(&x).sqrt().grad(&x).eval());
+    assert_eq!(0.25, var(&x).ln().grad(&x).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn simplify_transcendental() {
+    let x: f64 = 2.;
+    let s: Expr<ops::Exp<&f64>> = (--var(&x)).exp().simplify();
+    assert_eq!(x.exp(), s.eval());
+    let s: Expr<ops::Powi<Lit<f64>>> = (lit(1.) * lit(2.)).powi(3).simplify();
+    assert_eq!(8., s.eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn grad_trig_runtime() {
+    let x: f64 = 0.5;
+    assert_close(x.sin(), var(&x).sin().eval());
+    assert_close(x.cos(), var(&x).sin().grad(&x).eval());
+    assert_close(-x.sin(), var(&x).cos().grad(&x).eval());
+    assert_close(1. / (x.cos() * x.cos()), var(&x).tan().grad(&x).eval());
+    assert_close(1. / (1. - x * x).sqrt(), var(&x).asin().grad(&x).eval());
+    assert_close(-1. / (1. - x * x).sqrt(), var(&x).acos().grad(&x).eval());
+    assert_close(1. / (1. + x * x), var(&x).atan().grad(&x).eval());
+    assert_close(-x.sin(), var(&x).sin().grad(&x).grad(&x).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn grad_hyperbolic_runtime() {
+    let x: f64 = 0.5;
+    assert_close(x.cosh(), var(&x).sinh().grad(&x).eval());
+    assert_close(x.sinh(), var(&x).cosh().grad(&x).eval());
+    assert_close(1. / (x.cosh() * x.cosh()), var(&x).tanh().grad(&x).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn grad_atan2_runtime() {
+    let y: f64 = 3.;
+    let x: f64 = 4.;
+    let expr = var(&y).atan2(var(&x));
+    assert_close(y.atan2(x), (&expr).eval());
+    assert_close(x / 25., (&expr).grad(&y).eval());
+    assert_close(-y / 25., expr.grad(&x).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn grad_trig_chain_rule_f32() {
+    let theta: f32 = 0.25;
+    let r: f32 = 2.;
+    let x = var(&r) * var(&theta).cos(); // polar to Cartesian
+    let error = (&x).grad(&theta).eval() + r * theta.sin();
+    assert!(error.abs() < 1e-6);
+    assert_eq!(theta.cos(), x.grad(&r).eval());
+}
+
+#[test]
+fn eval_dual_matches_grad() {
+    let x: i32 = 3;
+    let y: i32 = 5;
+    let expr = var(&x) * var(&x) * var(&y) - 2 * var(&y) + 7; // x^2 y - 2y + 7
+    let dual = expr.eval_dual(&x);
+    assert_eq!(expr.eval(), dual.value);
+    assert_eq!(expr.grad(&x).eval(), dual.deriv);
+    assert_eq!(
+        Dual {
+            value: 42,
+            deriv: 7
+        },
+        expr.eval_dual(&y)
+    );
+}
+
+#[test]
+fn eval_dual_div_rem_shift() {
+    let x: f64 = 2.;
+    let dual = ((var(&x) * var(&x)) / (var(&x) + var(&x) * var(&x))).eval_dual(&x);
+    assert_eq!(1. / 9., dual.deriv);
+    let a: i32 = 17;
+    let b: i32 = 5;
+    assert_eq!(
+        Dual {
+            value: 2,
+            deriv: -3
+        },
+        (var(&a) % var(&b)).eval_dual(&b)
+    );
+    let n: u32 = 4;
+    assert_eq!(
+        Dual {
+            value: 48,
+            deriv: 16
+        },
+        ((var(&a) - 14) << var(&n)).eval_dual(&a)
+    );
+}
+
+#[test]
+fn eval_dual_bool() {
+    let a = true;
+    let b = false;
+    assert_eq!(
+        Dual {
+            value: false,
+            deriv: true
+        },
+        (var(&a) & var(&b)).eval_dual(&b)
+    );
+    assert_eq!(
+        Dual {
+            value: true,
+            deriv: false
+        },
+        (var(&a) | var(&b)).eval_dual(&b)
+    );
+}
+
+#[test]
+fn const_eval_dual() {
+    const X: i32 = 4;
+    const DUAL: Dual<i32> = (-(var_id::<0, _>(&X) * var_id::<0, _>(&X))).eval_dual(Id::<0>);
+    assert_eq!(
+        Dual {
+            value: -16,
+            deriv: -8
+        },
+        DUAL
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn eval_dual_transcendental() {
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let expr =
+        (var(&x) * var(&y)).sin().exp() + var(&x).powf(var(&y)) - var(&x).powi(3).atan2(var(&y));
+    let dual = (&expr).eval_dual(&x);
+    assert_close((&expr).eval(), dual.value);
+    assert_close((&expr).grad(&x).eval(), dual.deriv);
+    assert_close(expr.grad(&y).eval(), expr.eval_dual(&y).deriv);
+    assert_eq!(0., var(&0_f64).powf(lit(2.)).eval_dual(&0.).deriv);
+    let (zero, two): (f64, f64) = (0., 2.);
+    assert_eq!(0., var(&zero).powf(var(&two)).eval_dual(&two).deriv);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn backward_matches_grad() {
+    let x: i32 = 3;
+    let y: i32 = 5;
+    let z: i32 = 7;
+    let expr = var(&x) * var(&x) * var(&y) - 2 * var(&y) + var(&y) / var(&x);
+    let grads = (&expr).backward();
+    assert_eq!(2, grads.len());
+    assert_eq!((&expr).grad(&x).eval(), grads.wrt(&x));
+    assert_eq!((&expr).grad(&y).eval(), grads.wrt(&y));
+    assert_eq!(None, grads.get(&z));
+    assert_eq!(0, grads.wrt(&z));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn backward_many_parameters() {
+    let w: [f64; 4] = [1., 2., 3., 4.];
+    let input: [f64; 4] = [0.5, -1., 2., 0.25];
+    let loss = (var(&w[0]) * var(&input[0])
+        + var(&w[1]) * var(&input[1])
+        + var(&w[2]) * var(&input[2])
+        + var(&w[3]) * var(&input[3])
+        - 1.)
+        .powi(2);
+    let grads = loss.backward();
+    let residual: f64 = w.iter().zip(input).map(|(w, i)| w * i).sum::<f64>() - 1.;
+    for (w, i) in w.iter().zip(&input) {
+        assert_close(2. * residual * i, grads.wrt(w));
+        assert_close(2. * residual * w, grads.wrt(i));
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn backward_transcendental() {
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let expr = (var(&x) * var(&y)).sin().exp() + var(&x).powf(var(&y))
+        - var(&x).powi(3).atan2(var(&y))
+        + var(&x).sqrt().ln() * var(&y).tanh();
+    let grads = (&expr).backward();
+    assert_close((&expr).grad(&x).eval(), grads.wrt(&x));
+    assert_close(expr.grad(&y).eval(), grads.wrt(&y));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn backward_pow_edge_cases() {
+    // `n u^(n - 1)` even where `n - 1` doesn't fit an `i32`
+    let n = i32::MIN as f64;
+    for u in [1_f64, 2., 0.5, 0.] {
+        let expected = n * u.powf(n - 1.);
+        assert_eq!(expected, var(&u).powi(i32::MIN).backward().wrt(&u));
+        assert_eq!(var(&u).powi(i32::MIN).grad(&u).eval(), expected);
+    }
+    // `0^v` is flat in `v`, rather than `0 * ln 0 = NaN`
+    let (zero, two): (f64, f64) = (0., 2.);
+    let grads = var(&zero).powf(var(&two)).backward();
+    assert_eq!(0., grads.wrt(&two));
+    assert_eq!(0., grads.wrt(&zero));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn backward_rem_shift_var_id() {
+    let a: i32 = 17;
+    let b: i32 = 5;
+    let n: u32 = 2;
+    let grads = ((var_id::<0, _>(&a) % var(&b)) << var(&n)).backward();
+    assert_eq!(4, grads.wrt(&a));
+    assert_eq!(-12, grads.wrt(&b));
+}
+
+#[test]
+fn grad_all_tuple() {
+    let a: i32 = 2;
+    let b: i32 = 3;
+    let c: i32 = 5;
+    let expr = var(&a) * var(&b) + var(&b) * var(&c);
+    let (da, db, dc) = expr.grad_all((&a, &b, &c));
+    assert_eq!(3, da.eval());
+    assert_eq!(7, db.eval());
+    assert_eq!(3, dc.eval());
+    let (da,) = expr.grad_all((&a,));
+    assert_eq!(3, da.eval());
+}
+
+#[test]
+fn grad_array_runtime() {
+    let x: [f64; 3] = [1., 2., 3.];
+    let expr = var(&x[0]) * var(&x[1]) * var(&x[2]);
+    let grads = expr.grad_array(&[&x[0], &x[1], &x[2]]).map(|g| g.eval());
+    assert_eq!([6., 3., 2.], grads);
+}
+
+#[test]
+fn grad_all_mixed_ids() {
+    let x: i32 = 4;
+    let y: i32 = 6;
+    let expr = var_id::<0, _>(&x) * var(&y);
+    let (dx, dy) = expr.grad_all((Id::<0>, &y));
+    #[cfg(feature = "id-folding")]
+    assert_eq!((6, 4), (*dx.eval(), dy.eval()));
+    #[cfg(not(feature = "id-folding"))]
+    assert_eq!((6, 4), (dx.eval(), dy.eval()));
+}
+
+#[test]
+fn jacobian_forward_tuple() {
+    let x: i32 = 2;
+    let y: i32 = 3;
+    let f = (var(&x) * var(&y), var(&x) - var(&y) * var(&y), 4 * var(&x));
+    assert_eq!([[3, 2], [1, -6], [4, 0]], jacobian_forward(&f, &[&x, &y]));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn jacobian_modes_agree() {
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let z: f64 = -1.;
+    let f = [var(&x) * var(&y) + var(&z), var(&y) * var(&z) + var(&x)];
+    let vars = [&x, &y, &z];
+    let reverse = jacobian_reverse(&f, &vars);
+    assert_eq!([[2., 0.5, 1.], [1., -1., 2.]], reverse);
+    assert_eq!(jacobian_forward(&f, &vars), reverse);
+    assert_eq!(reverse, jacobian(&f, &vars));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn jacobian_newton_step() {
+    // Solve x^2 + y^2 = 4, xy = 1 from (2, 0.5) with one Newton step
+    let x: f64 = 2.;
+    let y: f64 = 0.5;
+    let f = (
+        var(&x).powi(2) + var(&y).powi(2) - 4.,
+        var(&x) * var(&y) - 1.,
+    );
+    let [[a, b], [c, d]] = jacobian(&f, &[&x, &y]);
+    assert_eq!([[4., 1.], [0.5, 2.]], [[a, b], [c, d]]);
+    let (f0, f1) = (f.0.eval(), f.1.eval());
+    let det = a * d - b * c;
+    let (dx, dy) = ((d * f0 - b * f1) / det, (a * f1 - c * f0) / det);
+    assert_close(2. - 0.5 / 7.5, x - dx);
+    assert_close(0.5 + 0.125 / 7.5, y - dy);
+}
+
+#[test]
+fn hessian_polynomial() {
+    let x: i32 = 2;
+    let y: i32 = 3;
+    // f = x^2 y + x y^3, so H = [[2y, 2x + 3y^2], [2x + 3y^2, 6xy]]
+    let f = var(&x) * var(&x) * var(&y) + var(&x) * var(&y) * var(&y) * var(&y);
+    assert_eq!([[6, 31], [31, 36]], hessian(&f, &[&x, &y]));
+    #[cfg(feature = "std")]
+    assert_eq!([12 - 31, 62 - 36], hvp(&f, &[&x, &y], &[2, -1]));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn hvp_matches_hessian() {
+    let x: f64 = 0.5;
+    let y: f64 = -1.5;
+    let z: f64 = 2.;
+    let f = var(&x).sin() * var(&y).exp() + var(&y) * var(&z).powi(3);
+    let vars = [&x, &y, &z];
+    let h = hessian(&f, &vars);
+    assert_close(-x.sin() * y.exp(), h[0][0]);
+    assert_close(x.cos() * y.exp(), h[0][1]);
+    assert_close(3. * z * z, h[1][2]);
+    assert_close(6. * y * z, h[2][2]);
+    for i in 0..3 {
+        for j in 0..3 {
+            assert_eq!(h[i][j], h[j][i]);
+        }
+    }
+    let v = [1., -2., 0.25];
+    let hv = hvp(&f, &vars, &v);
+    for i in 0..3 {
+        assert_close((0..3).map(|j| h[i][j] * v[j]).sum(), hv[i]);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn hvp_forward_over_reverse() {
+    let x: f64 = 1.5;
+    let y: f64 = 0.5;
+    let f = var(&x).powf(var(&y)) * var(&y).cos() + var(&x).powi(3) / var(&y);
+    let vars = [&x, &y];
+    let v = [0.5, -2.];
+    let second = |i: usize, j: usize| f.grad(vars[i]).grad(vars[j]).eval();
+    let hv = hvp(&f, &vars, &v);
+    for (i, &hv) in hv.iter().enumerate() {
+        assert_close(second(i, 0) * v[0] + second(i, 1) * v[1], hv);
+    }
+    // The same pass yields the gradient itself
+    let grads = f.backward_along(direction(&vars, &v));
+    assert_close(f.grad(&x).eval(), grads.wrt_dual(&x).value);
+    assert_close(f.grad(&y).eval(), grads.wrt_dual(&y).value);
+    // Gradient expressions (e.g. with `Monomial`s) too: third derivatives
+    let g = (var(&x).powi(4) * var(&y)).grad(&x);
+    let hv = hvp(&g, &vars, &v);
+    let third = |j: usize, k: usize| g.grad(vars[j]).grad(vars[k]).eval();
+    for (j, &hv) in hv.iter().enumerate() {
+        assert_close(third(j, 0) * v[0] + third(j, 1) * v[1], hv);
+    }
+    let z: f64 = 3.;
+    assert_eq!(
+        Dual {
+            value: 0.,
+            deriv: 0.
+        },
+        grads.wrt_dual(&z)
+    );
+}
+
+#[test]
+fn jvp_matches_jacobian() {
+    let x: i32 = 2;
+    let y: i32 = 3;
+    let f = (var(&x) * var(&y), var(&x) - var(&y) * var(&y), var(&x) * 4);
+    let vars = [&x, &y];
+    let j = jacobian_forward(&f, &vars);
+    let v = [5, -1];
+    assert_eq!(
+        [0, 1, 2].map(|i| j[i][0] * v[0] + j[i][1] * v[1]),
+        jvp(&f, &vars, &v)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn vjp_matches_jacobian() {
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let z: f64 = -1.;
+    let f = [var(&x) * var(&y) + var(&z), var(&y) * var(&z) + var(&x)];
+    let vars = [&x, &y, &z];
+    let j = jacobian(&f, &vars);
+    let c = [3., -0.5];
+    assert_eq!(
+        [0, 1, 2].map(|k| c[0] * j[0][k] + c[1] * j[1][k]),
+        vjp(&f, &vars, &c)
+    );
+    let v = [1., 2., -4.];
+    assert_eq!(
+        [0, 1].map(|i| (0..3).map(|k| j[i][k] * v[k]).sum::<f64>()),
+        jvp(&f, &vars, &v)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn display_infix() {
+    let x: i32 = -3;
+    let y: i32 = 2;
+    let names = [(&x, "x"), (&y, "y")];
+    assert_eq!(
+        "-x + 2*y",
+        (-var(&x) + 2 * var(&y)).named(&names).to_string()
+    );
+    assert_eq!("-(-3) + 2*2", (-var(&x) + 2 * var(&y)).to_string());
+    assert_eq!("-(-x)", (--var(&x)).named(&names).to_string());
+    assert_eq!("-3*2", (var(&x) * var(&y)).to_string());
+    assert_eq!(
+        "x - (y - x)",
+        (var(&x) - (var(&y) - var(&x))).named(&names).to_string()
+    );
+    assert_eq!(
+        "x - y - x",
+        (var(&x) - var(&y) - var(&x)).named(&names).to_string()
+    );
+    assert_eq!(
+        "(x + y)*(x - y)",
+        ((var(&x) + var(&y)) * (var(&x) - var(&y)))
+            .named(&names)
+            .to_string()
+    );
+    assert_eq!("-(x*y)", (-(var(&x) * var(&y))).named(&names).to_string());
+    assert_eq!(
+        "x << y & 1 | !x ^ y",
+        (((var(&x) << var(&y)) & 1) | (!var(&x) ^ var(&y)))
+            .named(&names)
+            .to_string()
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn display_gradient() {
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let names = [(&x, "x"), (&y, "y")];
+    let f = var(&x).sin() * var(&y).powi(2) + var(&x).atan2(var(&y));
+    assert_eq!(
+        "sin(x)*pow(y, 2) + atan2(x, y)",
+        f.named(&names).to_string()
+    );
+    assert_eq!(
+        "cos(x)*0*pow(y, 2) + sin(x)*(2*pow(y, 1)*1) + (y*0 - x*1)/(y*y + x*x)",
+        f.grad(&y).named(&names).to_string()
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn latex_functions() {
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let names = [(&x, "x"), (&y, "\\theta")];
+    let f = var(&x).sin().powi(2) / var(&y).sqrt() - var(&x).exp() * var(&y).ln();
+    assert_eq!(
+        "\\frac{\\left(\\sin\\left(x\\right)\\right)^{2}}{\\sqrt{\\theta}} - e^{x} \\cdot \\ln\\left(\\theta\\right)",
+        f.named(&names).to_latex()
+    );
+    let g = (var(&x) + var(&y)).powf(-var(&y)) * var(&x).atan2(var(&y)).tanh();
+    assert_eq!(
+        "\\left(x + \\theta\\right)^{-\\theta} \\cdot \\tanh\\left(\\operatorname{atan2}\\left(x, \\theta\\right)\\right)",
+        g.named(&names).to_latex()
+    );
+    assert_eq!("0.5^{2}", var(&x).powi(2).to_latex());
+    assert_eq!("-\\left(-x\\right)", (--var(&x)).named(&names).to_latex());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn latex_gradient() {
+    let x: f64 = 0.5;
+    let names = [(&x, "x")];
+    let dfdx = (1. / var(&x).sqrt()).grad(&x);
+    assert_eq!(
+        "\\frac{-\\left(1 \\cdot \\frac{1}{2 \\cdot \\sqrt{x}}\\right)}{\\sqrt{x} \\cdot \\sqrt{x}}",
+        dfdx.named(&names).to_latex()
+    );
+    let bits = !var(&true) & var(&false) | var(&true) ^ var(&false);
+    assert_eq!(
+        "\\lnot true \\land false \\lor true \\oplus false",
+        bits.to_latex()
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn dot_graph() {
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let f = var(&x).sin() * var(&y) + var(&x);
+    let (px, py) = (format!("\"{:p}\"", &x), format!("\"{:p}\"", &y));
+    let dot = f.named(&[(&x, "x"), (&y, "y")]).to_dot();
+    assert_eq!(
+        format!(
+            "digraph {{
+    n0 [label=\"Add\"];
+    n1 [label=\"Mul\"];
+    n2 [label=\"sin\"];
+    {px} [label=\"x\"];
+    n2 -> {px};
+    n1 -> n2;
+    {py} [label=\"y\"];
+    n1 -> {py};
+    n0 -> n1;
+    {px} [label=\"x\"];
+    n0 -> {px};
+}}
+"
+        ),
+        dot
+    );
+    let valued = f.named(&[(&x, "x"), (&y, "y")]).dot().values().to_string();
+    assert!(valued.contains(&format!("n0 [label=\"Add\\n= {}\"];", x.sin() * y + x)));
+    assert!(valued.contains(&format!("{px} [label=\"x\\n= 0.5\"];")));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn dot_gradient_constants() {
+    let x: i32 = 3;
+    let g = (var_id::<0, _>(&x) * var_id::<0, _>(&x)).grad(&x);
+    let dot = g.dot().values().to_string();
+    assert!(dot.contains("n0 [label=\"Add\\n= 6\"];"));
+    assert!(dot.contains("n1 [label=\"Mul\\n= 3\"];"));
+    assert!(dot.contains("n2 [label=\"1\"];"));
+    assert!(dot.contains("[label=\"var 0 = 3\"];"));
+    assert_eq!(6, dot.matches(" -> ").count());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn rust_fn_source() {
+    let x: i32 = 3;
+    let y: i32 = -4;
+    let f = var_id::<0, _>(&x) * var_id::<1, _>(&y) - 3 * var_id::<0, _>(&x);
+    assert_eq!(
+        "pub const fn f(x: i32, y: i32) -> i32 {\n    x * y - 3i32 * x\n}\n",
+        f.to_rust_fn("f", &["x", "y"])
+    );
+    #[cfg(feature = "id-folding")]
+    let g = f.grad_id::<1>();
+    // Without `id-folding`, gradients hold `Delta`s, which only fold away by value
+    #[cfg(not(feature = "id-folding"))]
+    let g = crate::dynamic::IntoDyn::simplify_dyn(f.grad_id::<1>());
+    assert_eq!(
+        "#[allow(unused_variables)]\npub const fn dfdy(x: i32, y: i32) -> i32 {\n    x\n}\n",
+        g.named(&()).to_rust_fn("dfdy", &["x", "y"])
+    );
+    // Generated above, pasted here
+    const fn f_(x: i32, y: i32) -> i32 {
+        x * y - 3i32 * x
+    }
+    #[allow(unused_variables)]
+    const fn dfdy(x: i32, y: i32) -> i32 {
+        x
+    }
+    assert_eq!(f.eval(), f_(x, y));
+    assert_eq!(f.grad(&y).eval(), dfdy(x, y));
+    assert_eq!(
+        "#[allow(unused_variables)]\npub const fn g(x: i32, y: i32) -> i32 {\n    -x\n}\n",
+        (-var_id::<0, _>(&x)).to_rust_fn("g", &["x", "y"])
+    );
+    // Named, but not a parameter: baked in
+    assert_eq!(
+        "pub const fn h(x: i32) -> i32 {\n    x + -4i32\n}\n",
+        (var(&x) + var(&y))
+            .named(&[(&x, "x"), (&y, "y")])
+            .to_rust_fn("h", &["x"])
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn rust_fn_named_methods() {
+    let x: f64 = -0.5;
+    let c: f64 = -2.;
+    let f = (-var(&x)).sin().powi(2) + var(&x).atan2(var(&c));
+    let source = f.named(&[(&x, "x")]).to_rust_fn("f", &["x"]);
+    assert_eq!(
+        "pub fn f(x: f64) -> f64 {\n    (-x).sin().powi(2) + x.atan2(-2f64)\n}\n",
+        source
+    );
+    fn f_(x: f64) -> f64 {
+        (-x).sin().powi(2) + x.atan2(-2f64)
+    }
+    assert_close(f.eval(), f_(x));
+    let dfdx = f.grad(&x).named(&[(&x, "x")]).to_rust_fn("dfdx", &["x"]);
+    assert!(dfdx.starts_with("pub fn dfdx(x: f64) -> f64 {\n    "));
+    assert_eq!(
+        "pub const fn h(x: f64) -> f64 {\n    -(-x) + -(-2f64)\n}\n",
+        (--var(&x) + -var(&c))
+            .named(&[(&x, "x")])
+            .to_rust_fn("h", &["x"])
+    );
+    let b = var_id::<0, _>(&true) ^ !var(&false);
+    assert_eq!(
+        "pub const fn g(a: bool) -> bool {\n    a ^ !false\n}\n",
+        b.to_rust_fn("g", &["a"])
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn c_fn_shares_temporaries() {
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let (a, b) = (var_id::<0, _>(&x), var_id::<1, _>(&y));
+    let f = a.sin() * b + (a.sin() * b).exp() / b.sqrt();
+    #[cfg(feature = "id-folding")]
+    let (dfdx, dfdy) = (f.grad_id::<0>(), f.grad_id::<1>());
+    #[cfg(not(feature = "id-folding"))]
+    let (dfdx, dfdy) = {
+        use crate::dynamic::IntoDyn;
+        (
+            f.grad_id::<0>().simplify_dyn(),
+            f.grad_id::<1>().simplify_dyn(),
+        )
+    };
+    let source = crate::codegen::c_fn("f", &["x", "y"], &(), &[&f, &dfdx, &dfdy]);
+    assert_eq!(
+        "void f(const double x, const double y, double out[3]) {
+    const double t0 = sin(x);
+    const double t1 = t0 * y;
+    const double t2 = exp(t1);
+    const double t3 = sqrt(y);
+    const double t4 = cos(x);
+    const double t5 = t4 * y;
+    const double t6 = t3 * t3;
+    out[0] = t1 + t2 / t3;
+    out[1] = t5 + t2 * t5 * t3 / t6;
+    out[2] = t0 + (t2 * t0 * t3 - t2 * (1.0 / (2.0 * t3))) / t6;
+}
+",
+        source
+    );
+    assert_eq!(
+        "double g(const double x, const double y) {
+    const double t0 = sin(x);
+    const double t1 = t0 * y;
+    return t1 + exp(t1) / sqrt(y);
+}
+",
+        f.to_c_fn("g", &["x", "y"])
+    );
+    let main = format!(
+        "#include <math.h>\n#include <stdbool.h>\n#include <stdint.h>\n#include <stdio.h>\n{source}\n\
+         int main(void) {{ double out[3]; f({x:?}, {y:?}, out); printf(\"%.17g %.17g %.17g\\n\", out[0], out[1], out[2]); return 0; }}\n"
+    );
+    let Some(output) = run_c(&main) else {
+        return; // No host C compiler: the golden source above has to do
+    };
+    let outputs: Vec<f64> = output
+        .split_whitespace()
+        .map(|v| v.parse().unwrap())
+        .collect();
+    assert_close(f.eval(), outputs[0]);
+    assert_close(dfdx.eval(), outputs[1]);
+    assert_close(dfdy.eval(), outputs[2]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn c_fn_integers_and_booleans() {
+    let x: i32 = -7;
+    let y: i32 = 3;
+    let f = (var_id::<0, _>(&x) % var_id::<1, _>(&y)) * 2_i32
+        - !var_id::<0, _>(&x)
+        - (var_id::<1, _>(&y) << 2_i32);
+    assert_eq!(
+        "int32_t f(const int32_t x, const int32_t y) {\n    return x % y * 2 - ~x - (y << 2);\n}\n",
+        f.to_c_fn("f", &["x", "y"])
+    );
+    let p: bool = true;
+    let q: bool = false;
+    let names = [(&p, "p"), (&q, "q")];
+    let g = !var(&p) & var(&q) | var(&p) ^ var(&q);
+    assert_eq!(
+        "bool g(const bool p, const bool q) {\n    return (!p & q) | (p ^ q);\n}\n",
+        g.named(&names).to_c_fn("g", &["p", "q"])
+    );
+    let main = format!(
+        "#include <stdbool.h>\n#include <stdint.h>\n#include <stdio.h>\n{}{}\
+         int main(void) {{ printf(\"%d %d\\n\", (int)f({x}, {y}), (int)g({p}, {q})); return 0; }}\n",
+        f.to_c_fn("f", &["x", "y"]),
+        g.named(&names).to_c_fn("g", &["p", "q"]),
+    );
+    let Some(output) = run_c(&main) else {
+        return;
+    };
+    assert_eq!(format!("{} {}", f.eval(), g.eval() as i32), output.trim());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn c_fn_double_negation() {
+    let x: f64 = 0.5;
+    let c: f64 = -2.;
+    let f = -(-var_id::<0, _>(&x)) + -var(&c);
+    let source = f.to_c_fn("f", &["x"]);
+    assert_eq!(
+        "double f(const double x) {\n    return -(-x) + -(-2.0);\n}\n",
+        source
+    );
+    let main = format!(
+        "#include <stdio.h>\n{source}\
+         int main(void) {{ printf(\"%.17g\\n\", f({x:?})); return 0; }}\n"
+    );
+    let Some(output) = run_c(&main) else {
+        return;
+    };
+    assert_close(f.eval(), output.trim().parse().unwrap());
+}
+
+/// Compile and run a C program with the host compiler (`$CC` or `cc`), returning its output, or `None` if there's no compiler.
+#[cfg(feature = "std")]
+fn run_c(source: &str) -> Option<String> {
+    use std::{
+        process::Command,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    static PROGRAMS: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir();
+    let stem = format!(
+        "dxpr-{}-{}",
+        std::process::id(),
+        PROGRAMS.fetch_add(1, Ordering::Relaxed)
+    );
+    let (c, exe) = (dir.join(format!("{stem}.c")), dir.join(stem));
+    std::fs::write(&c, source).unwrap();
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".into());
+    let status = Command::new(compiler)
+        .args(["-std=c99", "-Wall", "-Werror", "-o"])
+        .arg(&exe)
+        .arg(&c)
+        .arg("-lm")
+        .status()
+        .ok()?;
+    assert!(status.success(), "generated C failed to compile:\n{source}");
+    let output = Command::new(&exe).output().unwrap();
+    let _ = std::fs::remove_file(&c);
+    let _ = std::fs::remove_file(&exe);
+    Some(String::from_utf8(output.stdout).unwrap())
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn parse_eval_grad() {
+    use crate::parse::parse;
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let vars = [(&x, "x"), (&y, "y")];
+    let f = parse("sin(x) * y + 3", &vars).unwrap();
+    assert_eq!(x.sin() * y + 3., (&f).eval());
+    assert_eq!(x.cos() * y, (&f).grad(&x).eval());
+    assert_eq!(x.sin(), (&f).grad(&y).eval());
+    assert_eq!("sin(x)*y + 3", f.named(&[(&x, "x"), (&y, "y")]).to_string());
+    // Mixes with static expressions
+    let z: f64 = -1.5;
+    let h = f.clone() * var(&z).exp();
+    assert_close((x.sin() * y + 3.) * z.exp(), (&h).eval());
+    assert_close((x.sin() * y + 3.) * z.exp(), (&h).grad(&z).eval());
+    let h = var(&z).exp() / f.clone(); // either side
+    assert_close(z.exp() / (x.sin() * y + 3.), (&h).grad(&z).eval());
+    assert_close(
+        -z.exp() * x.cos() * y / ((x.sin() * y + 3.) * (x.sin() * y + 3.)),
+        (&h).grad(&x).eval(),
+    );
+    let g = f - var(&z).exp();
+    assert_close(x.sin() * y + 3. - z.exp(), (&g).eval());
+    assert_close(x.cos() * y, (&g).grad(&x).eval());
+    assert_close(-z.exp(), (&g).grad(&z).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn dyn_expr_from_static() {
+    use crate::dynamic::{DynExpr, IntoDyn};
+    let x: f64 = 0.75;
+    let y: f64 = 1.5;
+    let (a, b) = (var(&x), var(&y));
+    let expected = -a.powi(3) * b.exp() / (1. + b.ln()) - a.sqrt() % b + a.powf(b).cos()
+        - b.atan2(a) * a.tanh();
+    let converted = DynExpr::from(expected);
+    assert_eq!(expected.eval(), (&converted).eval());
+    assert_eq!(expected.grad(&x).eval(), (&converted).grad(&x).eval());
+    assert_eq!(expected.grad(&y).eval(), (&converted).grad(&y).eval());
+    // Gradients convert too, and IDs survive
+    let f = var_id::<0, _>(&x) * var_id::<1, _>(&y);
+    let g = f.grad(Id::<0>).into_dyn();
+    assert_eq!(y, (&g).eval());
+    assert_eq!(x, (&f.into_dyn()).grad(Id::<1>).eval());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn dyn_expr_integers() {
+    use crate::dynamic::{DynExpr, IntoDyn};
+    let x: i32 = 3;
+    let y: i32 = -4;
+    let f: DynExpr<'_, i32> = (var(&x) * var(&y)).into_dyn() - 2 * var(&x);
+    assert_eq!(x * y - 2 * x, (&f).eval());
+    assert_eq!(y - 2, (&f).grad(&x).eval());
+    assert_eq!(x, f.grad(&y).eval());
+    let p: DynExpr<'_, i32> = [var(&x), var(&y), var(&x)].into_iter().product();
+    assert_eq!(x * y * x, (&p).eval());
+    assert_eq!(2 * x * y, p.grad(&x).eval());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn dyn_expr_integers_reject_float_nodes() {
+    use crate::dynamic::{DynExpr, Function, Unsupported};
+    let x: i32 = 3;
+    assert_eq!(Err(Unsupported("sin")), DynExpr::call(Function::Sin, &x));
+    assert_eq!(Err(Unsupported("powi")), DynExpr::powi(var(&x), 2));
+    assert_eq!(Err(Unsupported("powf")), DynExpr::powf(&x, lit(2)));
+    assert_eq!(Err(Unsupported("atan2")), DynExpr::atan2(&x, &x));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn dyn_expr_checked_constructors() {
+    use crate::dynamic::{DynExpr, Function};
+    let x: f64 = 0.5;
+    let y: f64 = 2.;
+    let f = DynExpr::call(Function::Sin, &x).unwrap() * DynExpr::powi(&y, 3).unwrap();
+    assert_eq!(x.sin() * y.powi(3), (&f).eval());
+    assert_eq!(3. * x.sin() * y * y, f.grad(&y).eval());
+    let g = DynExpr::atan2(&x, DynExpr::powf(&y, lit(0.5)).unwrap()).unwrap();
+    assert_eq!(x.atan2(y.sqrt()), g.eval());
+}
+
+// Without `std`, floating-point `DynExpr`s still do arithmetic
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+#[test]
+fn dyn_expr_floats_without_std() {
+    use crate::dynamic::{DynExpr, Function, IntoDyn, Unsupported};
+    let x: f64 = 1.5;
+    let y: f64 = -2.;
+    let f: DynExpr<'_, f64> = (var(&x) * var(&y)).into_dyn() / var(&x);
+    assert_eq!(y, (&f).eval());
+    assert_eq!(0., (&f).grad(&x).eval());
+    assert_eq!(1., f.grad(&y).eval());
+    assert_eq!(Err(Unsupported("exp")), DynExpr::call(Function::Exp, &x));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn dyn_expr_in_a_loop() {
+    use crate::dynamic::{DynExpr, IntoDyn};
+    let xs: [f64; 4] = [0.5, -1., 2., 3.];
+    // Polynomial 1 + 2 x0 + 3 x1^2 + 4 x2^3 + 5 x3^4, one term at a time
+    let mut p = lit(1.).into_dyn();
+    for (i, x) in xs.iter().enumerate() {
+        p = p + lit((i + 2) as f64) * var(x).powi(i as i32 + 1);
+    }
+    let value = 1. + 2. * xs[0] + 3. * xs[1] * xs[1] + 4. * xs[2].powi(3) + 5. * xs[3].powi(4);
+    assert_eq!(value, (&p).eval());
+    for (i, x) in xs.iter().enumerate() {
+        let n = i as f64 + 1.;
+        assert_eq!((n + 1.) * n * x.powi(i as i32), (&p).grad(x).eval());
+    }
+    // Chosen at runtime, stored side by side
+    let fs: Vec<DynExpr<'_, f64>> = (0..3)
+        .map(|i| match i {
+            0 => var(&xs[0]).sin().into_dyn(),
+            1 => -var(&xs[0]).into_dyn() / var(&xs[1]),
+            _ => var(&xs[0]).into_dyn() % lit(0.25),
+        })
+        .collect();
+    let values: Vec<f64> = fs.iter().map(|f| f.eval()).collect();
+    assert_eq!([xs[0].sin(), -xs[0] / xs[1], xs[0] % 0.25][..], values);
+    assert_eq!(-1. / xs[1], (&fs[1]).grad(&xs[0]).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn sum_product_arrays() {
+    let x: f64 = 2.;
+    let y: f64 = -3.;
+    let z: f64 = 0.5;
+    let s = sum([var(&x), var(&y), var(&z)]);
+    let p = product([var(&x), var(&y), var(&z)]);
+    assert_eq!(x + y + z, s.eval());
+    assert_eq!(1., s.grad(&y).eval());
+    assert_eq!(x * y * z, p.eval());
+    assert_eq!(x * z, p.grad(&y).eval());
+    assert_eq!(z, p.grad(&x).grad(&y).eval());
+    assert_eq!(0., p.grad(&x).grad(&x).eval());
+    assert_eq!(0., sum::<&f64, 0>([]).eval());
+    assert_eq!(1., product::<&f64, 0>([]).eval());
+    let names = [(&x, "x"), (&y, "y"), (&z, "z")];
+    assert_eq!("x + y + z", s.named(&names).to_string());
+    assert_eq!("(x + y + z)*(x*y*z)", (s * p).named(&names).to_string());
+    assert_eq!("x \\cdot y \\cdot z", p.named(&names).to_latex());
+    assert!(p
+        .named(&names)
+        .to_rust_fn("f", &["x", "y", "z"])
+        .contains("x * y * z"));
+    // Runtime `Delta`s fold away before generating code
+    let grad = [p.grad(&x).simplify_dyn(), p.grad(&y).simplify_dyn()];
+    let grad = [&grad[0] as &dyn crate::print::Node, &grad[1]];
+    let source = crate::codegen::c_fn("f", &["x", "y", "z"], &names, &grad);
+    assert!(source.contains("out[0] = y * z;"));
+    assert!(source.contains("out[1] = x * z;"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn product_grad_thousand_factors() {
+    let xs: [f64; 1000] = core::array::from_fn(|i| 1. + i as f64 * 1e-4);
+    let p = product::<_, 1000>(core::array::from_fn(|i| var(&xs[i])));
+    let others = |k: usize| -> f64 {
+        xs.iter()
+            .enumerate()
+            .filter(|&(i, _)| i != k)
+            .map(|(_, x)| x)
+            .product()
+    };
+    for k in [0, 500, 999] {
+        let (expected, actual) = (others(k), p.grad(&xs[k]).eval());
+        assert!(
+            (expected - actual).abs() < 1e-12 * expected,
+            "{expected} != {actual}"
+        );
+    }
+    let y: f64 = 2.;
+    assert_eq!(0., p.grad(&y).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn simplify_dyn_runtime_identities() {
+    use crate::dynamic::IntoDyn;
+    let x: f64 = 4.;
+    let y: f64 = 2.;
+    let names = [(&x, "x"), (&y, "y")];
+    let dvdx = (var(&x) * var(&x) * var(&y)).grad(&x);
+    // `Delta`s are only known at runtime, so type-level simplification can't drop them
+    assert_eq!(
+        "(1*x + x*1)*y + x*x*0",
+        (&dvdx).simplify().named(&names).to_string()
+    );
+    assert_eq!(
+        "(x + x)*y",
+        (&dvdx).simplify_dyn().named(&names).to_string()
+    );
+    let d2 = (&dvdx).grad(&x);
+    assert_eq!(
+        "(1*1 + 1*1)*y + (1*x + x*1)*0 + (1*x + x*1)*0",
+        (&d2).named(&names).to_string()
+    );
+    assert_eq!("2*y", (&d2).simplify_dyn().named(&names).to_string());
+    assert_eq!((&d2).eval(), (&d2).simplify_dyn().eval());
+    assert_eq!("0", d2.grad(&x).simplify_dyn().named(&names).to_string());
+    // Double negations, unit powers, and products with a single nonzero gradient
+    let e = (-(-var(&x)).powi(1) * var(&y)).into_dyn();
+    assert_eq!("x*y", e.simplify().named(&names).to_string());
+    let p = crate::dynamic::product([var(&x).into_dyn(), var(&y).into_dyn(), lit(1.).into_dyn()]);
+    assert_eq!("y", (&p).grad(&x).simplify().named(&names).to_string());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn dyn_product_grad_thousand_factors() {
+    use crate::dynamic::{product, DynExpr};
+    let x: f64 = 1.;
+    let cs: Vec<f64> = (0..1000).map(|i| i as f64 * 1e-4).collect();
+    let p: DynExpr<'_, f64> = product(cs.iter().map(|&c| var(&x) + c));
+    let dp = (&p).grad(&x);
+    let (value, slope) = ((&p).eval(), (&dp).eval());
+    let expected = value * cs.iter().map(|c| 1. / (x + c)).sum::<f64>();
+    assert!(
+        (expected - slope).abs() < 1e-12 * expected,
+        "{expected} != {slope}"
+    );
+    // Second order through the product rule node
+    let q: DynExpr<'_, f64> = product([var(&x) + 1., var(&x) + 2., var(&x) + 3.]);
+    let dq = (&q).grad(&x);
+    assert_eq!(
+        "(x + 2)*(x + 3) + (x + 1)*(x + 3) + (x + 1)*(x + 2)",
+        (&dq).named(&[(&x, "x")]).to_string()
+    );
+    assert_eq!(26., (&dq).eval());
+    assert_eq!(18., dq.grad(&x).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn sum_product_iterators() {
+    use crate::dynamic::{product, sum, DynExpr};
+    let xs: Vec<f64> = (1..=1000).map(|i| i as f64 / 1000.).collect();
+    let s: DynExpr<'_, f64> = xs.iter().map(|x| var(x).powi(2)).sum();
+    assert_close(xs.iter().map(|x| x * x).sum(), (&s).eval());
+    assert_eq!(2. * xs[499], (&s).grad(&xs[499]).eval());
+    let p = product(xs[..3].iter());
+    assert_eq!(xs[0] * xs[1] * xs[2], (&p).eval());
+    assert_eq!(xs[0] * xs[2], (&p).grad(&xs[1]).eval());
+    // Mixed types, and static nodes converted
+    let (a, b) = (var(&xs[0]), var(&xs[1]));
+    let mixed = sum([
+        a.sin().into_dyn(),
+        (a * b).into_dyn(),
+        crate::expr::sum([a, b]).into_dyn(),
+    ]);
+    assert_close(xs[0].sin() + xs[0] * xs[1] + xs[0] + xs[1], (&mixed).eval());
+    assert_close(xs[0].cos() + xs[1] + 1., (&mixed).grad(&xs[0]).eval());
+    assert_eq!(
+        "sin(a) + a*b + (a + b)",
+        mixed.named(&[(&xs[0], "a"), (&xs[1], "b")]).to_string()
+    );
+    assert_eq!(0., (&sum::<f64, [DynExpr<'_, f64>; 0]>([])).eval());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn parse_matches_static() {
+    use crate::parse::parse;
+    let x: f64 = 0.25;
+    let y: f64 = 1.75;
+    let vars = [(&x, "x"), (&y, "y")];
+    let source = "-x^2 * exp(y) / (1 + ln(y)) - sqrt(x) % y + pow(x, y) + atan2(y, x) * tan(x) \
+                  - asin(x) * acos(x) + atan(y) * sinh(x) / cosh(y) + tanh(x)^-3 + cos(x^y)";
+    let parsed = parse(source, &vars).unwrap();
+    let (a, b) = (var(&x), var(&y));
+    let expected =
+        -a.powi(2) * b.exp() / (1. + b.ln()) - a.sqrt() % b + a.powf(b) + b.atan2(a) * a.tan()
+            - a.asin() * a.acos()
+            + b.atan() * a.sinh() / b.cosh()
+            + a.tanh().powi(-3)
+            + a.powf(b).cos();
+    assert_close(expected.eval(), (&parsed).eval());
+    assert_close(expected.grad(&x).eval(), (&parsed).grad(&x).eval());
+    assert_close(expected.grad(&y).eval(), (&parsed).grad(&y).eval());
+    // What `Display` prints parses back to the same tree
+    let printed = parsed.named(&[(&x, "x"), (&y, "y")]).to_string();
+    assert_eq!(parsed, parse(&printed, &vars).unwrap());
+    // Edge cases of powers, against their analytic derivatives: `2.5 u^1.5` and `n u^(n - 1)` for `n = i32::MIN`
+    let (zero, two): (f64, f64) = (0., 2.);
+    let f = parse("x^y", &[(&zero, "x"), (&two, "y")]).unwrap();
+    assert_eq!(0., f.grad(&two).eval()); // `0^v` is flat, not `0 * ln 0`
+    let converted = var(&zero).powf(var(&two)).grad(&two).into_dyn();
+    assert_eq!(0., converted.eval());
+    let n = i32::MIN as f64;
+    for u in [0_f64, 2., 0.5] {
+        let f = parse("x^2.5 + x^-2147483648", &[(&u, "x")]).unwrap();
+        assert_eq!(2.5 * u.powf(1.5) + n * u.powf(n - 1.), f.grad(&u).eval());
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn parse_errors_have_spans() {
+    use crate::parse::{parse, ErrorKind, ParseError};
+    let x: f64 = 1.;
+    let vars = [(&x, "x")];
+    let error = |source: &str| parse(source, &vars).map(|_| ()).unwrap_err();
+    let at = |span: core::ops::Range<usize>, kind| ParseError { span, kind };
+    assert_eq!(at(9..9, ErrorKind::UnexpectedEnd), error("sin(x) * "));
+    assert_eq!(at(4..5, ErrorKind::UnknownVariable), error("x + w"));
+    assert_eq!(at(0..3, ErrorKind::UnknownFunction), error("foo(x)"));
+    assert_eq!(
+        at(
+            0..6,
+            ErrorKind::Arity {
+                expected: 2,
+                found: 1
+            }
+        ),
+        error("pow(x)")
+    );
+    assert_eq!(
+        at(2..3, ErrorKind::Expected("an operator or the end of input")),
+        error("x $ y")
+    );
+    assert_eq!(at(4..5, ErrorKind::UnexpectedChar('$')), error("x + $"));
+    assert_eq!(at(3..4, ErrorKind::Expected("`)`")), error("(x x"));
+    assert_eq!(at(0..4, ErrorKind::InvalidNumber), error("1..2"));
+    assert_eq!("unknown variable at 4..5", error("x + w").to_string());
+    // What was expected survives the end of the input
+    assert_eq!(at(2..2, ErrorKind::Expected("`)`")), error("(x"));
+    assert_eq!(at(7..7, ErrorKind::Expected("`)`")), error("atan2(x"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn parse_too_deep() {
+    use crate::parse::{parse, ErrorKind, ParseError, MAX_DEPTH};
+    let x: f64 = 1.;
+    let vars = [(&x, "x")];
+    let error = |source: &str| parse(source, &vars).map(|_| ()).unwrap_err();
+    let too_deep = |at: usize, len: usize| ParseError {
+        span: at..at + len,
+        kind: ErrorKind::TooDeep,
+    };
+    // Untrusted input fails instead of overflowing the stack
+    let minus = "-".repeat(1_000_000) + "x";
+    assert_eq!(too_deep(MAX_DEPTH, 1), error(&minus));
+    let open = "(".repeat(1_000_000) + "x";
+    assert_eq!(too_deep(MAX_DEPTH, 1), error(&open));
+    let calls = "sin(".repeat(MAX_DEPTH + 1) + "x";
+    assert_eq!(too_deep(4 * MAX_DEPTH, 3), error(&calls));
+    let powers = "x^".repeat(MAX_DEPTH + 1) + "x";
+    assert_eq!(too_deep(2 * MAX_DEPTH + 1, 1), error(&powers));
+    // Up to the limit is fine
+    let source = "(".repeat(MAX_DEPTH) + "x" + &")".repeat(MAX_DEPTH);
+    assert_eq!(x, parse(&source, &vars).unwrap().eval());
+    let source = "-".repeat(MAX_DEPTH) + "x";
+    assert_eq!(x, (&parse(&source, &vars).unwrap()).eval());
+    let source = "x^".repeat(MAX_DEPTH) + "x";
+    let f = parse(&source, &vars).unwrap();
+    assert!((&f).grad(&x).eval().is_finite());
+    assert_eq!(
+        format!(
+            "nested more than {MAX_DEPTH} levels deep at {MAX_DEPTH}..{}",
+            MAX_DEPTH + 1
+        ),
+        error(&minus).to_string()
+    );
+}